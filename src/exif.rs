@@ -97,3 +97,42 @@ impl<'a> ProvideUnit<'a> for &'a Exif {
         self.get_field(tag, ifd_num)
     }
 }
+
+#[cfg(feature = "std")]
+impl Exif {
+    /// Serializes this `Exif`'s fields back into a TIFF-structured
+    /// Exif byte stream, in the struct's own byte order.
+    ///
+    /// This supports only the primary/thumbnail/Exif/GPS/Interop IFD
+    /// layout that `experimental::Writer` understands; a field in any
+    /// other context or IFD number makes this return an error.  The
+    /// result round-trips: `Reader::read_raw(&exif.serialize()?)`
+    /// reproduces the same fields.
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() { sub(); }
+    /// # fn sub() -> Option<()> {
+    /// # use exif::{In, Reader, Tag};
+    /// # let file = std::fs::File::open("tests/exif.jpg").unwrap();
+    /// # let exif = Reader::new().read_from_container(
+    /// #     &mut std::io::BufReader::new(&file)).unwrap();
+    /// let buf = exif.serialize().unwrap();
+    /// let reparsed = Reader::new().read_raw(buf).unwrap();
+    /// match reparsed.get_field(Tag::XResolution, In::PRIMARY)?.value {
+    ///     exif::Value::Rational(ref v) => assert_eq!(v, &[(72, 1).into()]),
+    ///     ref v => panic!("unexpected value {:?}", v),
+    /// }
+    /// # Some(()) }
+    /// ```
+    pub fn serialize(&self) -> Result<Vec<u8>, crate::Error> {
+        let mut writer = crate::writer::Writer::new();
+        writer.set_little_endian(self.little_endian);
+        for f in self.fields() {
+            writer.push_field(f);
+        }
+        let mut buf = Vec::new();
+        writer.write(&mut buf)?;
+        Ok(buf)
+    }
+}
@@ -52,7 +52,14 @@ Based on the following: http://fileformats.archiveteam.org/wiki/Fujifilm_RAF
 mod marker {
     // The first byte of a marker
     pub const TIFF1_JPEG_PTR_OFFSET: usize = 84;
-    pub const TIFF2_PTR_OFFSET: usize = 100;
+    // "CFA Offset" in the offset directory (see the format doc comment
+    // above): the start of the uncompressed RAW sensor data, *not* a
+    // second TIFF IFD pointer.
+    pub const CFA_OFFSET_PTR_OFFSET: usize = 100;
+    // "CFA Length", immediately following `CFA_OFFSET_PTR_OFFSET`.
+    pub const CFA_LENGTH_PTR_OFFSET: usize = 104;
+    // "CFA Header Offset": points at the tag-record directory read by
+    // `parse_fuji_raw`, not at the CFA pixel data itself.
     pub const TAGS_PTR_OFFSET: usize = 92;
 }
 
@@ -149,6 +156,103 @@ pub enum RafMakernotes {
     Parallax = 0xb211,
 }
 
+/// Human-readable labels for `RafMakernotes` tags whose raw numeric
+/// codes aren't self-explanatory, looked up by
+/// `display_makernote_value`. `Field::display_value` does not consult
+/// these tables (see `display_makernote_value`). Tables live next to
+/// `RafMakernotes` so a new tag's mapping can be added alongside its
+/// variant.
+mod print_conv {
+    use super::RafMakernotes;
+
+    pub const WHITE_BALANCE: &[(u32, &str)] = &[
+        (0x000, "Auto"),
+        (0x100, "Daylight"),
+        (0x200, "Cloudy"),
+        (0x300, "Fluorescent (Daylight)"),
+        (0x301, "Fluorescent (Warm White)"),
+        (0x302, "Fluorescent (Cool White)"),
+        (0x400, "Incandescent"),
+        (0xf00, "Custom"),
+    ];
+
+    pub const FILM_MODE: &[(u32, &str)] = &[
+        (0x000, "Provia/Standard"),
+        (0x100, "Velvia/Vivid"),
+        (0x110, "Astia/Soft"),
+        (0x120, "Classic Chrome"),
+        (0x130, "Pro Neg. Hi"),
+        (0x131, "Pro Neg. Std"),
+        (0x200, "Monochrome"),
+        (0x201, "Monochrome + Ye Filter"),
+        (0x202, "Monochrome + R Filter"),
+        (0x203, "Monochrome + G Filter"),
+        (0x300, "Sepia"),
+        (0x400, "Acros"),
+        (0x401, "Acros + Ye Filter"),
+        (0x402, "Acros + R Filter"),
+        (0x403, "Acros + G Filter"),
+        (0x500, "Classic Negative"),
+    ];
+
+    pub const DYNAMIC_RANGE: &[(u32, &str)] = &[(1, "Standard"), (3, "Wide")];
+
+    pub const FOCUS_MODE: &[(u32, &str)] = &[(0, "Single Point"), (1, "Zone"), (2, "Wide/Tracking")];
+
+    pub const FUJI_FLASH_MODE: &[(u32, &str)] = &[
+        (0, "Auto"),
+        (1, "On"),
+        (2, "Off"),
+        (3, "Red-eye Reduction"),
+        (4, "External"),
+    ];
+
+    pub const SCENE_RECOGNITION: &[(u32, &str)] = &[
+        (0, "Unrecognized"),
+        (768, "Portrait Image"),
+        (769, "Landscape Image"),
+        (770, "Night Scene"),
+        (771, "Macro"),
+    ];
+
+    pub const SHUTTER_TYPE: &[(u32, &str)] = &[
+        (0, "Mechanical"),
+        (1, "Electronic"),
+        (2, "Electronic Front Curtain"),
+    ];
+
+    /// Looks up the descriptive string for a raw makernote value, if
+    /// the tag has a conversion table and the code is in it.
+    pub fn lookup(tag: RafMakernotes, code: u32) -> Option<&'static str> {
+        let table: &[(u32, &str)] = match tag {
+            RafMakernotes::WhiteBalance => WHITE_BALANCE,
+            RafMakernotes::FilmMode => FILM_MODE,
+            RafMakernotes::DynamicRange => DYNAMIC_RANGE,
+            RafMakernotes::FocusMode => FOCUS_MODE,
+            RafMakernotes::FujiFlashMode => FUJI_FLASH_MODE,
+            RafMakernotes::SceneRecognition => SCENE_RECOGNITION,
+            RafMakernotes::ShutterType => SHUTTER_TYPE,
+            _ => return None,
+        };
+        table.iter().find(|&&(c, _)| c == code).map(|&(_, s)| s)
+    }
+}
+
+/// Converts a `Context::MakerNote` field's raw value to a
+/// human-readable string, if `tagnum` names a `RafMakernotes` variant
+/// with a conversion table and the stored code is in it. It leaves
+/// `Field::value` untouched so the raw code is still available to
+/// callers that want it.
+///
+/// `Value::display_as` does not call this function, so
+/// `Field::display_value` and `DisplayValueUnit` do not use these
+/// conversions; it is called only from this module's tests.
+pub(crate) fn display_makernote_value(tagnum: u16, value: &Value) -> Option<String> {
+    let tag = RafMakernotes::n(tagnum)?;
+    let code = value.get_uint(0)?;
+    print_conv::lookup(tag, code).map(str::to_string)
+}
+
 /// These are only related to the additional FujiIFD in RAF files
 #[derive(Debug, Copy, Clone, PartialEq, enumn::N, FromPrimitive)]
 #[repr(u16)]
@@ -187,10 +291,101 @@ pub enum FujiIFD {
 //     RAFData = 0xc000,
 // }
 
+/// A face detected by the camera's face-detection, decoded from the
+/// `RafMakernotes` face tags (`FacesDetected`, `FacePositions`,
+/// `FaceElementPositions`/`FaceElementTypes`, and `FaceRecInfo`).
+#[derive(Debug, Clone)]
+pub struct DetectedFace {
+    /// The face's bounding box in full-image pixel space, as
+    /// `(left, top, right, bottom)`.
+    pub bounding_box: (i16, i16, i16, i16),
+    /// Sub-element rectangles (eyes, etc.), if the camera reported any.
+    pub elements: Vec<FaceElement>,
+    /// The recognized person's name, if this face matched a saved
+    /// face-recognition record.
+    pub name: Option<String>,
+    /// The recognized person's birthday (8-byte ASCII, e.g.
+    /// `"19700101"`), if present.
+    pub birthday: Option<String>,
+}
+
+/// A sub-element (eye, etc.) of a `DetectedFace`.
+#[derive(Debug, Clone)]
+pub struct FaceElement {
+    pub element_type: u16,
+    pub rect: (i16, i16, i16, i16),
+}
+
+fn short_values(value: &Value) -> &[u16] {
+    match value {
+        Value::Short(v) => v,
+        _ => &[],
+    }
+}
+
+fn rect_at(coords: &[i16], i: usize) -> Option<(i16, i16, i16, i16)> {
+    let base = i * 4;
+    let c = coords.get(base..base + 4)?;
+    Some((c[0], c[1], c[2], c[3]))
+}
+
+// `FaceRecInfo` is a block of fixed-size records, one per recognized
+// face: a NUL-terminated name followed by an 8-byte ASCII birthday.
+// The exact record size isn't documented anywhere we can reach, so it
+// is derived from the blob length and the known face count rather
+// than hard-coded.
+fn parse_face_rec_info(value: &Value, count: usize) -> Vec<(String, String)> {
+    let bytes: &[u8] = match value {
+        Value::Undefined(b, _) => b,
+        Value::Byte(b) => b,
+        _ => return Vec::new(),
+    };
+    if count == 0 {
+        return Vec::new();
+    }
+    let record_size = bytes.len() / count;
+    if record_size < 8 {
+        return Vec::new();
+    }
+    bytes
+        .chunks(record_size)
+        .take(count)
+        .map(|rec| {
+            let birthday_start = rec.len() - 8;
+            let name_bytes = &rec[..birthday_start];
+            let name_end = name_bytes
+                .iter()
+                .position(|&b| b == 0)
+                .unwrap_or(name_bytes.len());
+            let name = String::from_utf8_lossy(&name_bytes[..name_end]).into_owned();
+            let birthday = String::from_utf8_lossy(&rec[birthday_start..]).into_owned();
+            (name, birthday)
+        })
+        .collect()
+}
+
+/// A single sub-frame of a pixel-shift / multi-exposure ("M-RAW") RAF
+/// file, as described by the sub-frame directory pointed to by the
+/// `PixelShiftOffset` Makernote tag.
+#[derive(Debug, Clone)]
+pub struct MRawFrame {
+    /// Byte offset and length of this frame's CFA (raw sensor) data.
+    pub cfa_offset: u32,
+    pub cfa_length: u32,
+    /// Exposure bias applied to this frame, in the camera's own units.
+    pub exposure_bias: i32,
+    /// This frame's pixel-shift offset relative to the base frame.
+    pub shift_x: i16,
+    pub shift_y: i16,
+}
+
 #[derive(Debug)]
 pub struct FujiParser {
     pub jpeg_exif: Option<Exif>,
     pub raw_exif: Option<Exif>,
+    /// Sub-frames of a pixel-shift / multi-exposure RAF, or empty if
+    /// the file has no M-RAW directory.
+    pub mraw_frames: Vec<MRawFrame>,
 }
 
 impl Default for FujiParser {
@@ -198,6 +393,7 @@ impl Default for FujiParser {
         Self {
             jpeg_exif: None,
             raw_exif: None,
+            mraw_frames: Vec::new(),
         }
     }
 }
@@ -206,6 +402,119 @@ pub fn is_fuji_raf(buf: &[u8]) -> bool {
     buf[0..8] == b"FUJIFILM"[..]
 }
 
+/// Byte size of one unit of a standard TIFF type code, or 0 for an
+/// unrecognized type.
+fn tiff_type_size(typ: u16) -> usize {
+    match typ {
+        1 | 2 | 6 | 7 => 1,
+        3 | 8 => 2,
+        4 | 9 | 11 => 4,
+        5 | 10 | 12 => 8,
+        _ => 0,
+    }
+}
+
+/// Decodes one FujiIFD entry's value, following `valbuf`'s 4-byte
+/// value-or-offset field to its external location when the value
+/// doesn't fit inline. Returns `None` for every type code other than
+/// BYTE, SHORT, and LONG, whatever the entry's tag.
+fn decode_fuji_ifd_value<R>(
+    reader: &mut R,
+    typ: u16,
+    cnt: usize,
+    valbuf: [u8; 4],
+) -> Result<Option<Value>, Error>
+where
+    R: BufRead + Seek,
+{
+    let unit = tiff_type_size(typ);
+    if unit == 0 {
+        return Ok(None);
+    }
+    let vallen = unit
+        .checked_mul(cnt)
+        .ok_or(Error::InvalidFormat("Invalid FujiIFD entry count"))?;
+    let bytes = if vallen <= 4 {
+        valbuf[..vallen].to_vec()
+    } else {
+        let value_offset = u32::from_be_bytes(valbuf) as u64;
+        let file_len = reader.seek(SeekFrom::End(0))?;
+        if value_offset > file_len || file_len - value_offset < vallen as u64 {
+            return Err(Error::InvalidFormat("FujiIFD field value exceeds file length"));
+        }
+        reader.seek(SeekFrom::Start(value_offset))?;
+        let mut buf = vec![0u8; vallen];
+        reader
+            .read_exact(&mut buf)
+            .map_err(|_| Error::InvalidFormat("Truncated FujiIFD field value"))?;
+        buf
+    };
+    Ok(match typ {
+        1 => Some(Value::Byte(bytes)),
+        3 => Some(Value::Short(
+            bytes
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect(),
+        )),
+        4 => Some(Value::Long(
+            bytes
+                .chunks_exact(4)
+                .map(|c| u32::from_be_bytes([c[0], c[1], c[2], c[3]]))
+                .collect(),
+        )),
+        _ => None,
+    })
+}
+
+/// Reads the raw sensor-geometry/white-balance sub-IFD that the main
+/// RAF tag directory's `FujiIFD` entry points to. Unlike the
+/// length-prefixed records in the surrounding directory, this is an
+/// ordinary big-endian TIFF-style IFD: a 2-byte entry count followed
+/// by 12-byte entries (tag, type, count, value-or-offset). Entries are
+/// selected by type, not by tag: every BYTE, SHORT, or LONG entry
+/// (dimensions, bit depth, strip layout, black level, the GRB
+/// white-balance triples, and any other tag of those types) is
+/// decoded, and entries of any other type are dropped.
+fn parse_fuji_ifd<R>(reader: &mut R, offset: u32) -> Result<Vec<IfdEntry>, Error>
+where
+    R: BufRead + Seek,
+{
+    reader.seek(SeekFrom::Start(offset as u64))?;
+    let count =
+        read16(reader).map_err(|_| Error::InvalidFormat("Truncated FujiIFD entry count"))?;
+
+    let mut entries = Vec::new();
+    for _ in 0..count {
+        let tag_value =
+            read16(reader).map_err(|_| Error::InvalidFormat("Truncated FujiIFD entry"))?;
+        let typ = read16(reader).map_err(|_| Error::InvalidFormat("Truncated FujiIFD entry"))?;
+        let cnt =
+            read32(reader).map_err(|_| Error::InvalidFormat("Truncated FujiIFD entry"))? as usize;
+        let mut valbuf = [0u8; 4];
+        reader
+            .read_exact(&mut valbuf)
+            .map_err(|_| Error::InvalidFormat("Truncated FujiIFD entry"))?;
+        let next_entry = reader.stream_position()?;
+
+        if tag_value != FujiIFD::FujiIFD as u16 {
+            if let Some(value) = decode_fuji_ifd_value(reader, typ, cnt, valbuf)? {
+                entries.push(IfdEntry {
+                    field: Field {
+                        tag: Tag(Context::FujiRaf, tag_value),
+                        ifd_num: In::PRIMARY,
+                        value,
+                    }
+                    .into(),
+                });
+            }
+        }
+
+        reader.seek(SeekFrom::Start(next_entry))?;
+    }
+    Ok(entries)
+}
+
 impl FujiParser {
     pub fn parse<R>(&mut self, reader: &mut R) -> Result<(), Error>
     where
@@ -215,6 +524,7 @@ impl FujiParser {
 
         // let _ = self.parse_sub(data, marker::TIFF1_JPEG_PTR_OFFSET + 12);
         self.raw_exif = self.parse_fuji_raw(reader).ok();
+        self.mraw_frames = self.parse_mraw(reader)?;
 
         if let (Some(raw_exif), Some(jpeg_exif)) = (&self.raw_exif, &self.jpeg_exif) {
             Exif::merge_two_exif(raw_exif, jpeg_exif);
@@ -224,6 +534,81 @@ impl FujiParser {
         Ok(())
     }
 
+    /// Reads the pixel-shift / multi-exposure M-RAW sub-frame
+    /// directory pointed to by the Makernote's `PixelShiftOffset` tag,
+    /// if present.  Returns an empty `Vec` for ordinary (non-pixel-
+    /// shift) files rather than an error; a present but malformed
+    /// directory is a hard `Error::InvalidFormat`.
+    fn parse_mraw<R>(&self, reader: &mut R) -> Result<Vec<MRawFrame>, Error>
+    where
+        R: BufRead + Seek,
+    {
+        let exif = match &self.jpeg_exif {
+            Some(exif) => exif,
+            None => return Ok(Vec::new()),
+        };
+        let field = |tagnum: u16| exif.get_field(Tag(Context::MakerNote, tagnum), In::PRIMARY);
+        let shots = field(RafMakernotes::PixelShiftShots as u16).and_then(|f| f.value.get_uint(0));
+        let offset =
+            field(RafMakernotes::PixelShiftOffset as u16).and_then(|f| f.value.get_uint(0));
+        let (shots, offset) = match (shots, offset) {
+            (Some(s), Some(o)) if s > 0 => (s as u64, o as u64),
+            _ => return Ok(Vec::new()),
+        };
+
+        let file_len = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(offset))?;
+        let frame_count =
+            read32(reader).map_err(|_| Error::InvalidFormat("Truncated M-RAW frame count"))? as u64;
+        if frame_count != shots {
+            return Err(Error::InvalidFormat(
+                "M-RAW frame count does not match PixelShiftShots",
+            ));
+        }
+
+        // 4 + 4 + 4 + 2 + 2 bytes per record (cfa_offset, cfa_length,
+        // exposure_bias, shift_x, shift_y). Bound frame_count against
+        // what could actually fit before trusting it as an allocation
+        // size.
+        const RECORD_SIZE: u64 = 16;
+        let remaining = file_len.saturating_sub(offset + 4);
+        if frame_count > remaining / RECORD_SIZE {
+            return Err(Error::InvalidFormat(
+                "M-RAW frame count exceeds what the file could hold",
+            ));
+        }
+
+        let mut frames = Vec::with_capacity(frame_count as usize);
+        for _ in 0..frame_count {
+            let cfa_offset = read32(reader)
+                .map_err(|_| Error::InvalidFormat("Truncated M-RAW frame record"))?;
+            let cfa_length = read32(reader)
+                .map_err(|_| Error::InvalidFormat("Truncated M-RAW frame record"))?;
+            let exposure_bias = read32(reader)
+                .map_err(|_| Error::InvalidFormat("Truncated M-RAW frame record"))?
+                as i32;
+            let shift_x = read16(reader)
+                .map_err(|_| Error::InvalidFormat("Truncated M-RAW frame record"))?
+                as i16;
+            let shift_y = read16(reader)
+                .map_err(|_| Error::InvalidFormat("Truncated M-RAW frame record"))?
+                as i16;
+            if cfa_offset as u64 + cfa_length as u64 > file_len {
+                return Err(Error::InvalidFormat(
+                    "M-RAW frame CFA range exceeds file length",
+                ));
+            }
+            frames.push(MRawFrame {
+                cfa_offset,
+                cfa_length,
+                exposure_bias,
+                shift_x,
+                shift_y,
+            });
+        }
+        Ok(frames)
+    }
+
     /// RAF format contains multiple TIFF and TIFF-like structures.
     /// This creates an IFD with all other IFD's found as sub IFD's.
     fn parse_fuji_raw<R>(&mut self, reader: &mut R) -> Result<Exif, Error>
@@ -243,9 +628,9 @@ impl FujiParser {
             _ => return Err(Error::NotFound("Invalid endian")),
         };
 
-        reader.seek(std::io::SeekFrom::Start(marker::TIFF2_PTR_OFFSET as u64))?;
+        reader.seek(std::io::SeekFrom::Start(marker::CFA_OFFSET_PTR_OFFSET as u64))?;
 
-        // let second_ifd_offset = read32(reader).expect("Failed to read second_ifd_offset");
+        // let cfa_offset = read32(reader).expect("Failed to read cfa_offset");
 
         // Read the primary RAF tags. JPEG exif tags will be read later.
         reader
@@ -316,6 +701,16 @@ impl FujiParser {
                         .into(),
                     });
                 }
+                // The FujiIFD pointer: a 4-byte absolute offset to a
+                // standard TIFF-style sub-IFD holding raw sensor
+                // geometry and white-balance levels.
+                _ if tag_value == FujiIFD::FujiIFD as u16 => {
+                    let fuji_ifd_offset =
+                        read32(reader).map_err(|_| Error::InvalidFormat("Truncated FujiIFD pointer"))?;
+                    let resume_at = reader.stream_position()?;
+                    entries.extend(parse_fuji_ifd(reader, fuji_ifd_offset)?);
+                    reader.seek(SeekFrom::Start(resume_at))?;
+                }
                 // Skip other tags
                 _ => {
                     reader.seek(SeekFrom::Current(len as i64))?;
@@ -356,6 +751,114 @@ impl FujiParser {
 
         Ok(exif)
     }
+
+    /// Decodes the Makernote's face-detection tags, if any, into
+    /// bounding boxes plus any recognized identity.  The face tags
+    /// live in the embedded JPEG thumbnail's Makernote, so this only
+    /// returns faces when `jpeg_exif` is present and was recognized
+    /// as a Fujifilm Makernote.
+    pub fn faces(&self) -> Vec<DetectedFace> {
+        let exif = match &self.jpeg_exif {
+            Some(exif) => exif,
+            None => return Vec::new(),
+        };
+        let field = |tagnum: u16| exif.get_field(Tag(Context::MakerNote, tagnum), In::PRIMARY);
+
+        let count = match field(RafMakernotes::FacesDetected as u16)
+            .and_then(|f| f.value.get_uint(0))
+        {
+            Some(n) => n as usize,
+            None => return Vec::new(),
+        };
+
+        let positions: Vec<i16> = field(RafMakernotes::FacePositions as u16)
+            .map(|f| short_values(&f.value).iter().map(|&v| v as i16).collect())
+            .unwrap_or_default();
+        let element_types: Vec<u16> = field(RafMakernotes::FaceElementTypes as u16)
+            .map(|f| short_values(&f.value).to_vec())
+            .unwrap_or_default();
+        let element_positions: Vec<i16> = field(RafMakernotes::FaceElementPositions as u16)
+            .map(|f| short_values(&f.value).iter().map(|&v| v as i16).collect())
+            .unwrap_or_default();
+        // FacesDetected is itself just another attacker-controlled Exif
+        // field, independent of how much face data is actually present;
+        // cap it to what `positions` can actually back (4 i16s per
+        // face) before using it to size the returned Vec, mirroring how
+        // parse_mraw (chunk2-2) bounds its on-disk count against what
+        // the file can actually hold.
+        let count = count.min(positions.len() / 4);
+
+        let rec_info = field(RafMakernotes::FaceRecInfo as u16)
+            .map(|f| parse_face_rec_info(&f.value, count));
+
+        let elements_per_face = if count != 0 {
+            element_types.len() / count
+        } else {
+            0
+        };
+
+        (0..count)
+            .map(|i| {
+                let bounding_box = rect_at(&positions, i).unwrap_or((0, 0, 0, 0));
+                let elements = (0..elements_per_face)
+                    .filter_map(|j| {
+                        let idx = i * elements_per_face + j;
+                        Some(FaceElement {
+                            element_type: *element_types.get(idx)?,
+                            rect: rect_at(&element_positions, idx)?,
+                        })
+                    })
+                    .collect();
+                let (name, birthday) = match rec_info.as_ref().and_then(|r| r.get(i)) {
+                    Some((name, birthday)) => (Some(name.clone()), Some(birthday.clone())),
+                    None => (None, None),
+                };
+                DetectedFace {
+                    bounding_box,
+                    elements,
+                    name,
+                    birthday,
+                }
+            })
+            .collect()
+    }
+}
+
+impl FujiParser {
+    /// Extracts the embedded full-resolution preview/thumbnail JPEG
+    /// (with its own Exif, including the Fujifilm Makernote) as raw
+    /// bytes. This is the same JPEG `parse` reads internally to
+    /// populate `jpeg_exif`, exposed directly for callers that just
+    /// want a fast preview without re-deriving its offset.
+    pub fn extract_jpeg_preview<R>(&self, reader: &mut R) -> Result<Vec<u8>, Error>
+    where
+        R: BufRead + Seek,
+    {
+        extract_jpeg_thumbnail(reader)
+    }
+
+    /// Byte offset and length of the embedded raw CFA (sensor) data,
+    /// read from the CFA pointers in the offset directory alongside
+    /// the embedded JPEG's own offset/length. Returns
+    /// `Error::InvalidFormat` if the range doesn't fit within the
+    /// file, so callers can hand the range to a demosaicer without
+    /// re-checking it.
+    pub fn cfa_range<R>(&self, reader: &mut R) -> Result<(u32, u32), Error>
+    where
+        R: BufRead + Seek,
+    {
+        let file_len = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(marker::CFA_OFFSET_PTR_OFFSET as u64))?;
+        let cfa_offset =
+            read32(reader).map_err(|_| Error::InvalidFormat("Truncated CFA offset"))?;
+        reader.seek(SeekFrom::Start(marker::CFA_LENGTH_PTR_OFFSET as u64))?;
+        let cfa_length =
+            read32(reader).map_err(|_| Error::InvalidFormat("Truncated CFA length"))?;
+        if cfa_offset as u64 + cfa_length as u64 > file_len {
+            return Err(Error::InvalidFormat("CFA range exceeds file length"));
+        }
+        Ok((cfa_offset, cfa_length))
+    }
 }
 
 fn extract_jpeg_exif<R>(reader: &mut R) -> Result<Exif, Error>
@@ -372,24 +875,28 @@ fn extract_jpeg_thumbnail<R>(reader: &mut R) -> Result<Vec<u8>, Error>
 where
     R: BufRead + Seek,
 {
+    let file_len = reader.seek(SeekFrom::End(0))?;
+
     // Read the Primary TIFF / JPEG Header which will parse almost all the common tags
-    reader
-        .seek(std::io::SeekFrom::Start(
-            marker::TIFF1_JPEG_PTR_OFFSET as u64,
-        ))
-        .expect("Failed to seek to TIFF1_JPEG_PTR_OFFSET");
+    reader.seek(std::io::SeekFrom::Start(
+        marker::TIFF1_JPEG_PTR_OFFSET as u64,
+    ))?;
 
-    let jpeg_offset = read32(reader).expect("Failed to read JPEG offset");
-    let jpeg_length = read32(reader).expect("Failed to read JPEG length");
+    let jpeg_offset =
+        read32(reader).map_err(|_| Error::InvalidFormat("Truncated JPEG offset"))?;
+    let jpeg_length =
+        read32(reader).map_err(|_| Error::InvalidFormat("Truncated JPEG length"))?;
 
-    reader
-        .seek(std::io::SeekFrom::Start(jpeg_offset.into()))
-        .expect("Failed to seek to JPEG offset");
+    if jpeg_offset as u64 + jpeg_length as u64 > file_len {
+        return Err(Error::InvalidFormat("JPEG range exceeds file length"));
+    }
+
+    reader.seek(std::io::SeekFrom::Start(jpeg_offset.into()))?;
 
     let mut embedded_jpeg = vec![0u8; jpeg_length as usize];
     reader
         .read_exact(&mut embedded_jpeg)
-        .expect("Failed to read JPEG data");
+        .map_err(|_| Error::InvalidFormat("Truncated JPEG data"))?;
 
     Ok(embedded_jpeg)
 }
@@ -412,3 +919,144 @@ fn read_raw(data: Vec<u8>) -> Result<Exif, Error> {
         little_endian: parser.little_endian,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    // A minimal synthetic RAF offset directory, built per the byte
+    // layout documented at the top of this file, with known-good CFA
+    // offset/length values at the end of it to check `cfa_range`
+    // against, not just that the returned range fits in the file.
+    // The file ends exactly where the CFA range does (`cfa_offset`
+    // must lie past the directory).
+    fn raf_header_with_cfa(cfa_offset: u32, cfa_length: u32) -> Vec<u8> {
+        let mut buf = vec![0u8; marker::CFA_LENGTH_PTR_OFFSET + 4];
+        buf[marker::CFA_OFFSET_PTR_OFFSET..marker::CFA_OFFSET_PTR_OFFSET + 4]
+            .copy_from_slice(&cfa_offset.to_be_bytes());
+        buf[marker::CFA_LENGTH_PTR_OFFSET..marker::CFA_LENGTH_PTR_OFFSET + 4]
+            .copy_from_slice(&cfa_length.to_be_bytes());
+        buf.resize(cfa_offset as usize + cfa_length as usize, 0);
+        buf
+    }
+
+    #[test]
+    fn cfa_range_reads_known_offset_and_length() {
+        let data = raf_header_with_cfa(200, 1024);
+        let parser = FujiParser::default();
+        let range = parser
+            .cfa_range(&mut Cursor::new(data))
+            .expect("cfa_range should succeed");
+        assert_eq!(range, (200, 1024));
+    }
+
+    #[test]
+    fn cfa_range_rejects_range_past_eof() {
+        let mut data = raf_header_with_cfa(200, 1024);
+        data.truncate(data.len() - 1);
+        let parser = FujiParser::default();
+        assert_err_pat!(
+            parser.cfa_range(&mut Cursor::new(data)),
+            Error::InvalidFormat("CFA range exceeds file length")
+        );
+    }
+
+    fn raf_header_with_jpeg(jpeg_data: &[u8]) -> Vec<u8> {
+        let jpeg_offset = marker::TIFF1_JPEG_PTR_OFFSET as u32 + 8;
+        let mut buf = vec![0u8; jpeg_offset as usize];
+        buf[marker::TIFF1_JPEG_PTR_OFFSET..marker::TIFF1_JPEG_PTR_OFFSET + 4]
+            .copy_from_slice(&jpeg_offset.to_be_bytes());
+        buf[marker::TIFF1_JPEG_PTR_OFFSET + 4..marker::TIFF1_JPEG_PTR_OFFSET + 8]
+            .copy_from_slice(&(jpeg_data.len() as u32).to_be_bytes());
+        buf.extend_from_slice(jpeg_data);
+        buf
+    }
+
+    #[test]
+    fn extract_jpeg_thumbnail_reads_known_bytes() {
+        let data = raf_header_with_jpeg(b"fake jpeg bytes");
+        let thumbnail = extract_jpeg_thumbnail(&mut Cursor::new(data)).unwrap();
+        assert_eq!(thumbnail, b"fake jpeg bytes");
+    }
+
+    #[test]
+    fn extract_jpeg_thumbnail_rejects_range_past_eof() {
+        let mut data = raf_header_with_jpeg(b"fake jpeg bytes");
+        data.truncate(data.len() - 1);
+        assert_err_pat!(
+            extract_jpeg_thumbnail(&mut Cursor::new(data)),
+            Error::InvalidFormat("JPEG range exceeds file length")
+        );
+    }
+
+    fn exif_with_makernote_fields(fields: Vec<(u16, Value)>) -> Exif {
+        let entries: Vec<IfdEntry> = fields
+            .into_iter()
+            .map(|(tagnum, value)| IfdEntry {
+                field: Field {
+                    tag: Tag(Context::MakerNote, tagnum),
+                    ifd_num: In::PRIMARY,
+                    value,
+                }
+                .into(),
+            })
+            .collect();
+        let entry_map = entries
+            .iter()
+            .enumerate()
+            .map(|(i, e)| (e.ifd_num_tag(), i))
+            .collect();
+        Exif {
+            buf: Vec::new(),
+            entries,
+            entry_map,
+            little_endian: true,
+        }
+    }
+
+    #[test]
+    fn faces_caps_count_to_available_position_data() {
+        let mut parser = FujiParser::default();
+        parser.jpeg_exif = Some(exif_with_makernote_fields(vec![
+            (RafMakernotes::FacesDetected as u16, Value::Long(vec![1000])),
+            (
+                RafMakernotes::FacePositions as u16,
+                Value::Short(vec![0, 0, 10, 10]),
+            ),
+        ]));
+        // FacesDetected claims 1000 faces, but FacePositions only backs
+        // one (4 i16 coordinates); the bogus count must not drive the
+        // returned Vec's size.
+        assert_eq!(parser.faces().len(), 1);
+    }
+
+    #[test]
+    fn decode_fuji_ifd_value_rejects_count_the_file_cannot_hold() {
+        // type 4 = Long (4-byte unit), cnt huge, value_offset = 0.
+        let data = vec![0u8; 16];
+        assert_err_pat!(
+            decode_fuji_ifd_value(&mut Cursor::new(data), 4, 0xffff_ffff, [0, 0, 0, 0]),
+            Error::InvalidFormat("FujiIFD field value exceeds file length")
+        );
+    }
+
+    #[test]
+    fn display_makernote_value_known_code() {
+        let value = Value::Long(vec![0x100]);
+        assert_eq!(
+            display_makernote_value(RafMakernotes::WhiteBalance as u16, &value),
+            Some("Daylight".to_string())
+        );
+    }
+
+    #[test]
+    fn display_makernote_value_unknown_code_or_tag() {
+        let value = Value::Long(vec![0xdead]);
+        assert_eq!(
+            display_makernote_value(RafMakernotes::WhiteBalance as u16, &value),
+            None
+        );
+        assert_eq!(display_makernote_value(RafMakernotes::Version as u16, &value), None);
+    }
+}
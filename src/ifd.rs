@@ -1,11 +1,16 @@
+#[cfg(feature = "std")]
 use std::fmt;
+#[cfg(not(feature = "std"))]
+use core::fmt;
+
+#[cfg(not(feature = "std"))]
+use alloc::format;
 
 use mutate_once::MutOnce;
 
 use crate::{
     endian::{BigEndian, Endian, LittleEndian},
     tag::UnitPiece,
-    util::{atou16, ctou32},
     value::{self, get_type_info},
     Error, Tag, Value,
 };
@@ -144,6 +149,28 @@ pub struct DateTime {
     pub offset: Option<i16>,
 }
 
+#[inline]
+fn digit_val(b: u8) -> Result<u32, Error> {
+    if b.is_ascii_digit() {
+        Ok((b - b'0') as u32)
+    } else {
+        Err(Error::InvalidFormat("Invalid digit in DateTime"))
+    }
+}
+
+// A specialized fixed-width decimal parser for the DateTime hot path:
+// a single pass over a known-length digit run with no per-field
+// bounds re-checks beyond the slice itself, used by `from_ascii` and
+// `parse_offset`.
+#[inline]
+fn parse_fixed_digits(data: &[u8]) -> Result<u32, Error> {
+    let mut acc: u32 = 0;
+    for &b in data {
+        acc = acc * 10 + digit_val(b)?;
+    }
+    Ok(acc)
+}
+
 impl DateTime {
     /// Parse an ASCII data of a DateTime field.  The range of a number
     /// is not validated, so, for example, 13 may be returned as the month.
@@ -163,12 +190,12 @@ impl DateTime {
             return Err(Error::InvalidFormat("Invalid DateTime delimiter"));
         }
         Ok(DateTime {
-            year: atou16(&data[0..4])?,
-            month: atou16(&data[5..7])? as u8,
-            day: atou16(&data[8..10])? as u8,
-            hour: atou16(&data[11..13])? as u8,
-            minute: atou16(&data[14..16])? as u8,
-            second: atou16(&data[17..19])? as u8,
+            year: parse_fixed_digits(&data[0..4])? as u16,
+            month: parse_fixed_digits(&data[5..7])? as u8,
+            day: parse_fixed_digits(&data[8..10])? as u8,
+            hour: parse_fixed_digits(&data[11..13])? as u8,
+            minute: parse_fixed_digits(&data[14..16])? as u8,
+            second: parse_fixed_digits(&data[17..19])? as u8,
             nanosecond: None,
             offset: None,
         })
@@ -182,7 +209,7 @@ impl DateTime {
             if c == b' ' {
                 break;
             }
-            subsec = subsec * 10 + ctou32(c)?;
+            subsec = subsec * 10 + digit_val(c)?;
             ndigits += 1;
             if ndigits >= 9 {
                 break;
@@ -208,8 +235,8 @@ impl DateTime {
         } else if data[3] != b':' {
             return Err(Error::InvalidFormat("Invalid OffsetTime delimiter"));
         }
-        let hour = atou16(&data[1..3])?;
-        let min = atou16(&data[4..6])?;
+        let hour = parse_fixed_digits(&data[1..3])?;
+        let min = parse_fixed_digits(&data[4..6])?;
         let offset = (hour * 60 + min) as i16;
         self.offset = Some(match data[0] {
             b'+' => offset,
@@ -218,6 +245,151 @@ impl DateTime {
         });
         Ok(())
     }
+
+    /// Returns the canonical Exif DateTime representation, the inverse
+    /// of `from_ascii`: a 19-byte `b"YYYY:MM:DD HH:MM:SS"` string.
+    pub fn to_ascii(&self) -> Vec<u8> {
+        format!(
+            "{:04}:{:02}:{:02} {:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+        .into_bytes()
+    }
+
+    /// Returns the SubSecTime-like representation of `nanosecond`, the
+    /// inverse of `parse_subsec`, or `None` if there is no subsecond
+    /// data.  Trailing zeros are dropped, since `parse_subsec` pads a
+    /// shorter value with them and cannot tell the difference.
+    pub fn subsec_to_ascii(&self) -> Option<Vec<u8>> {
+        let nanosecond = self.nanosecond?;
+        let mut digits = format!("{:09}", nanosecond);
+        let len = digits.trim_end_matches('0').len().max(1);
+        digits.truncate(len);
+        Some(digits.into_bytes())
+    }
+
+    /// Returns the OffsetTime-like representation of `offset`, the
+    /// inverse of `parse_offset`: a `b"+HH:MM"`/`b"-HH:MM"` string, or
+    /// `None` if there is no offset data.
+    pub fn offset_to_ascii(&self) -> Option<Vec<u8>> {
+        let offset = self.offset?;
+        let sign: u8 = if offset < 0 { b'-' } else { b'+' };
+        let minutes = offset.unsigned_abs();
+        Some(
+            format!(
+                "{}{:02}:{:02}",
+                sign as char,
+                minutes / 60,
+                minutes % 60
+            )
+            .into_bytes(),
+        )
+    }
+
+    /// Returns the number of seconds since the Unix epoch
+    /// (1970-01-01T00:00:00Z), treating a missing `offset` as UTC.
+    /// This lets `DateTime` values from different time zones be
+    /// compared and ordered correctly; see the `Ord` implementation.
+    pub fn to_unix_timestamp(&self) -> i64 {
+        let days = days_from_civil(self.year as i64, self.month as i64, self.day as i64);
+        days * 86400
+            + self.hour as i64 * 3600
+            + self.minute as i64 * 60
+            + self.second as i64
+            - self.offset.unwrap_or(0) as i64 * 60
+    }
+
+    /// Validates that every component is within its legal range.
+    /// `from_ascii` deliberately skips this (e.g. month 13 is
+    /// accepted), so callers that need to reject corrupt metadata can
+    /// call this explicitly, or use `from_ascii_validated`.
+    pub fn checked(&self) -> Result<(), Error> {
+        if !(1..=12).contains(&self.month) {
+            return Err(Error::InvalidFormat("DateTime month out of range"));
+        }
+        if self.day < 1 || self.day > days_in_month(self.year, self.month) {
+            return Err(Error::InvalidFormat("DateTime day out of range"));
+        }
+        if self.hour > 23 {
+            return Err(Error::InvalidFormat("DateTime hour out of range"));
+        }
+        if self.minute > 59 {
+            return Err(Error::InvalidFormat("DateTime minute out of range"));
+        }
+        if self.second > 60 {
+            return Err(Error::InvalidFormat("DateTime second out of range"));
+        }
+        if let Some(offset) = self.offset {
+            if offset < -24 * 60 || offset > 24 * 60 {
+                return Err(Error::InvalidFormat("DateTime offset out of range"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses an ASCII DateTime field like `from_ascii`, additionally
+    /// validating the component ranges via `checked`.
+    pub fn from_ascii_validated(data: &[u8]) -> Result<DateTime, Error> {
+        let dt = DateTime::from_ascii(data)?;
+        dt.checked()?;
+        Ok(dt)
+    }
+}
+
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        _ => 0,
+    }
+}
+
+fn is_leap_year(year: u16) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+// Howard Hinnant's days-from-civil algorithm, converting a proleptic
+// Gregorian calendar date into the number of days since 1970-01-01.
+// http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
+    let y = y - (m <= 2) as i64;
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+impl PartialEq for DateTime {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == core::cmp::Ordering::Equal
+    }
+}
+
+impl Eq for DateTime {}
+
+impl PartialOrd for DateTime {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Ordered by the instant in time, normalizing across time zones via
+// `to_unix_timestamp`, with `nanosecond` as a tiebreaker.  Note that
+// this means two `DateTime`s with different offsets but the same
+// instant compare equal even if their displayed fields differ.
+impl Ord for DateTime {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+        self.to_unix_timestamp()
+            .cmp(&other.to_unix_timestamp())
+            .then_with(|| {
+                self.nanosecond
+                    .unwrap_or(0)
+                    .cmp(&other.nanosecond.unwrap_or(0))
+            })
+    }
 }
 
 impl fmt::Display for DateTime {
@@ -230,6 +402,66 @@ impl fmt::Display for DateTime {
     }
 }
 
+// `DateTime::from_ascii` deliberately does not validate the range of
+// its components (e.g. month 13 is accepted), so converting to a
+// `chrono` type, which does validate, can fail.
+#[cfg(feature = "chrono")]
+impl std::convert::TryFrom<&DateTime> for chrono::NaiveDateTime {
+    type Error = Error;
+
+    fn try_from(dt: &DateTime) -> Result<Self, Error> {
+        let date = chrono::NaiveDate::from_ymd_opt(dt.year as i32, dt.month as u32, dt.day as u32)
+            .ok_or(Error::InvalidFormat("Invalid date in DateTime"))?;
+        let time = chrono::NaiveTime::from_hms_nano_opt(
+            dt.hour as u32,
+            dt.minute as u32,
+            dt.second as u32,
+            dt.nanosecond.unwrap_or(0),
+        )
+        .ok_or(Error::InvalidFormat("Invalid time in DateTime"))?;
+        Ok(chrono::NaiveDateTime::new(date, time))
+    }
+}
+
+// Only meaningful when `offset` is `Some`; the `FixedOffset` is built
+// from `offset`, interpreted as minutes east of UTC.
+#[cfg(feature = "chrono")]
+impl std::convert::TryFrom<&DateTime> for chrono::DateTime<chrono::FixedOffset> {
+    type Error = Error;
+
+    fn try_from(dt: &DateTime) -> Result<Self, Error> {
+        use chrono::TimeZone;
+
+        let offset_min = dt
+            .offset
+            .ok_or(Error::InvalidFormat("DateTime has no offset"))?;
+        let naive = chrono::NaiveDateTime::try_from(dt)?;
+        let offset = chrono::FixedOffset::east_opt(offset_min as i32 * 60)
+            .ok_or(Error::InvalidFormat("Invalid offset in DateTime"))?;
+        offset
+            .from_local_datetime(&naive)
+            .single()
+            .ok_or(Error::InvalidFormat("Ambiguous local time for offset"))
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<chrono::NaiveDateTime> for DateTime {
+    fn from(naive: chrono::NaiveDateTime) -> Self {
+        use chrono::{Datelike, Timelike};
+        DateTime {
+            year: naive.year() as u16,
+            month: naive.month() as u8,
+            day: naive.day() as u8,
+            hour: naive.hour() as u8,
+            minute: naive.minute() as u8,
+            second: naive.second() as u8,
+            nanosecond: Some(naive.nanosecond()),
+            offset: None,
+        }
+    }
+}
+
 impl Field {
     /// Returns an object that implements `std::fmt::Display` for
     /// printing the value of this field in a tag-specific format.
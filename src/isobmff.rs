@@ -0,0 +1,411 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::Error;
+
+const FTYP: [u8; 4] = *b"ftyp";
+const META: [u8; 4] = *b"meta";
+const MDAT: [u8; 4] = *b"mdat";
+const IINF: [u8; 4] = *b"iinf";
+const INFE: [u8; 4] = *b"infe";
+const ILOC: [u8; 4] = *b"iloc";
+const EXIF_ITEM_TYPE: [u8; 4] = *b"Exif";
+
+/// Returns true if `buf` looks like an ISOBMFF file (HEIF/HEIC/AVIF) by
+/// checking for a leading `ftyp` box.
+pub fn is_heif(buf: &[u8]) -> bool {
+    buf.len() >= 8 && &buf[4..8] == FTYP
+}
+
+struct Box_ {
+    typ: [u8; 4],
+    // Absolute file offset of the start of this box (its size field).
+    start: u64,
+    // Absolute file offset where the box's payload begins.
+    payload_start: u64,
+    payload_len: u64,
+}
+
+fn read_boxes<R: Read + Seek>(reader: &mut R, from: u64, to: u64) -> Result<Vec<Box_>, Error> {
+    let mut boxes = Vec::new();
+    let mut pos = from;
+    while pos < to {
+        reader.seek(SeekFrom::Start(pos))?;
+        let mut hdr = [0u8; 8];
+        reader.read_exact(&mut hdr)?;
+        let size32 = u32::from_be_bytes(hdr[0..4].try_into().unwrap()) as u64;
+        let mut typ = [0u8; 4];
+        typ.copy_from_slice(&hdr[4..8]);
+        let (header_len, size) = if size32 == 1 {
+            let mut ext = [0u8; 8];
+            reader.read_exact(&mut ext)?;
+            (16u64, u64::from_be_bytes(ext))
+        } else {
+            (8u64, size32)
+        };
+        if size < header_len || pos + size > to {
+            return Err(Error::InvalidFormat("Invalid ISOBMFF box size"));
+        }
+        boxes.push(Box_ {
+            typ,
+            start: pos,
+            payload_start: pos + header_len,
+            payload_len: size - header_len,
+        });
+        pos += size;
+    }
+    Ok(boxes)
+}
+
+// Locates the `meta` box's `iloc` child and the item id of the `Exif`
+// item within it, assuming the common case: `iinf`/`infe` version >= 2
+// item type `Exif`.
+fn find_exif_item_and_iloc<R: Read + Seek>(reader: &mut R) -> Result<(u32, Box_), Error> {
+    reader.seek(SeekFrom::Start(0))?;
+    let len = reader.seek(SeekFrom::End(0))?;
+    let top = read_boxes(reader, 0, len)?;
+    let meta = top
+        .iter()
+        .find(|b| b.typ == META)
+        .ok_or(Error::NotFound("meta box not found"))?;
+
+    // The meta box itself is a full box (4-byte version/flags) before
+    // its child boxes.
+    let meta_children = read_boxes(reader, meta.payload_start + 4, meta.payload_start + meta.payload_len)?;
+
+    let iinf = meta_children
+        .iter()
+        .find(|b| b.typ == IINF)
+        .ok_or(Error::NotFound("iinf box not found"))?;
+    let item_id = find_exif_item_id(reader, iinf)?;
+
+    let iloc = meta_children
+        .iter()
+        .find(|b| b.typ == ILOC)
+        .ok_or(Error::NotFound("iloc box not found"))?;
+    Ok((item_id, Box_ {
+        typ: iloc.typ,
+        start: iloc.start,
+        payload_start: iloc.payload_start,
+        payload_len: iloc.payload_len,
+    }))
+}
+
+// Locates the `iloc` extent (offset, length) holding the `Exif` item,
+// assuming the common case described by `find_exif_item_and_iloc`, and a
+// single `iloc` extent with `construction_method == 0` (i.e. the extent
+// lives at an absolute file offset, typically inside `mdat`).
+fn find_exif_extent<R: Read + Seek>(reader: &mut R) -> Result<(u64, u64), Error> {
+    let (item_id, iloc) = find_exif_item_and_iloc(reader)?;
+    find_iloc_extent(reader, &iloc, item_id)
+}
+
+fn find_exif_item_id<R: Read + Seek>(reader: &mut R, iinf: &Box_) -> Result<u32, Error> {
+    reader.seek(SeekFrom::Start(iinf.payload_start))?;
+    let mut fullbox = [0u8; 4];
+    reader.read_exact(&mut fullbox)?;
+    let version = fullbox[0];
+    let entry_count = if version == 0 {
+        let mut n = [0u8; 2];
+        reader.read_exact(&mut n)?;
+        u16::from_be_bytes(n) as u64
+    } else {
+        let mut n = [0u8; 4];
+        reader.read_exact(&mut n)?;
+        u32::from_be_bytes(n) as u64
+    };
+    let children_start = reader.stream_position()?;
+    let children = read_boxes(reader, children_start, iinf.payload_start + iinf.payload_len)?;
+    for (i, infe) in children.iter().enumerate() {
+        if infe.typ != INFE || i as u64 >= entry_count {
+            continue;
+        }
+        reader.seek(SeekFrom::Start(infe.payload_start))?;
+        let mut fb = [0u8; 4];
+        reader.read_exact(&mut fb)?;
+        let version = fb[0];
+        if version < 2 {
+            // item_type as a FourCC string only exists from version 2
+            // onward; older items are not relevant for Exif.
+            continue;
+        }
+        let item_id = if version == 2 {
+            let mut v = [0u8; 2];
+            reader.read_exact(&mut v)?;
+            u16::from_be_bytes(v) as u32
+        } else {
+            let mut v = [0u8; 4];
+            reader.read_exact(&mut v)?;
+            u32::from_be_bytes(v)
+        };
+        let mut protection_index = [0u8; 2];
+        reader.read_exact(&mut protection_index)?;
+        let mut item_type = [0u8; 4];
+        reader.read_exact(&mut item_type)?;
+        if item_type == EXIF_ITEM_TYPE {
+            return Ok(item_id);
+        }
+    }
+    Err(Error::NotFound("No Exif item in iinf"))
+}
+
+fn find_iloc_extent<R: Read + Seek>(
+    reader: &mut R,
+    iloc: &Box_,
+    item_id: u32,
+) -> Result<(u64, u64), Error> {
+    reader.seek(SeekFrom::Start(iloc.payload_start))?;
+    let mut fullbox = [0u8; 4];
+    reader.read_exact(&mut fullbox)?;
+    let version = fullbox[0];
+    let mut sizes = [0u8; 2];
+    reader.read_exact(&mut sizes)?;
+    let offset_size = sizes[0] >> 4;
+    let length_size = sizes[0] & 0xf;
+    let base_offset_size = sizes[1] >> 4;
+    let index_size = sizes[1] & 0xf;
+
+    let read_n = |reader: &mut R, n: u8| -> Result<u64, Error> {
+        if n == 0 {
+            return Ok(0);
+        }
+        let mut buf = [0u8; 8];
+        reader.read_exact(&mut buf[8 - n as usize..])?;
+        Ok(u64::from_be_bytes(buf))
+    };
+
+    let item_count = if version < 2 {
+        let mut v = [0u8; 2];
+        reader.read_exact(&mut v)?;
+        u16::from_be_bytes(v) as u32
+    } else {
+        let mut v = [0u8; 4];
+        reader.read_exact(&mut v)?;
+        u32::from_be_bytes(v)
+    };
+
+    for _ in 0..item_count {
+        let cur_item_id = if version < 2 {
+            let mut v = [0u8; 2];
+            reader.read_exact(&mut v)?;
+            u16::from_be_bytes(v) as u32
+        } else {
+            let mut v = [0u8; 4];
+            reader.read_exact(&mut v)?;
+            u32::from_be_bytes(v)
+        };
+        if version == 1 || version == 2 {
+            let mut v = [0u8; 2];
+            reader.read_exact(&mut v)?; // construction_method
+        }
+        let mut v = [0u8; 2];
+        reader.read_exact(&mut v)?; // data_reference_index
+        let base_offset = read_n(reader, base_offset_size)?;
+        let mut ec = [0u8; 2];
+        reader.read_exact(&mut ec)?;
+        let extent_count = u16::from_be_bytes(ec);
+        if cur_item_id == item_id {
+            if extent_count != 1 {
+                return Err(Error::InvalidFormat(
+                    "Only single-extent Exif items are supported",
+                ));
+            }
+            if index_size != 0 {
+                read_n(reader, index_size)?;
+            }
+            let extent_offset = read_n(reader, offset_size)?;
+            let extent_length = read_n(reader, length_size)?;
+            return Ok((base_offset + extent_offset, extent_length));
+        } else {
+            for _ in 0..extent_count {
+                if index_size != 0 {
+                    read_n(reader, index_size)?;
+                }
+                read_n(reader, offset_size)?;
+                read_n(reader, length_size)?;
+            }
+        }
+    }
+    Err(Error::NotFound("Exif item id not present in iloc"))
+}
+
+// Returns true if any `iloc` item other than `item_id` has an extent that
+// starts at or after `boundary` (i.e. would be invalidated by splicing a
+// different-sized replacement in before that point). Used by
+// `set_exif_attr` to detect the cases its single `patch_box_size` call
+// cannot safely handle.
+fn other_item_has_extent_after<R: Read + Seek>(
+    reader: &mut R,
+    iloc: &Box_,
+    item_id: u32,
+    boundary: u64,
+) -> Result<bool, Error> {
+    reader.seek(SeekFrom::Start(iloc.payload_start))?;
+    let mut fullbox = [0u8; 4];
+    reader.read_exact(&mut fullbox)?;
+    let version = fullbox[0];
+    let mut sizes = [0u8; 2];
+    reader.read_exact(&mut sizes)?;
+    let offset_size = sizes[0] >> 4;
+    let length_size = sizes[0] & 0xf;
+    let base_offset_size = sizes[1] >> 4;
+    let index_size = sizes[1] & 0xf;
+
+    let read_n = |reader: &mut R, n: u8| -> Result<u64, Error> {
+        if n == 0 {
+            return Ok(0);
+        }
+        let mut buf = [0u8; 8];
+        reader.read_exact(&mut buf[8 - n as usize..])?;
+        Ok(u64::from_be_bytes(buf))
+    };
+
+    let item_count = if version < 2 {
+        let mut v = [0u8; 2];
+        reader.read_exact(&mut v)?;
+        u16::from_be_bytes(v) as u32
+    } else {
+        let mut v = [0u8; 4];
+        reader.read_exact(&mut v)?;
+        u32::from_be_bytes(v)
+    };
+
+    for _ in 0..item_count {
+        let cur_item_id = if version < 2 {
+            let mut v = [0u8; 2];
+            reader.read_exact(&mut v)?;
+            u16::from_be_bytes(v) as u32
+        } else {
+            let mut v = [0u8; 4];
+            reader.read_exact(&mut v)?;
+            u32::from_be_bytes(v)
+        };
+        if version == 1 || version == 2 {
+            let mut v = [0u8; 2];
+            reader.read_exact(&mut v)?; // construction_method
+        }
+        let mut v = [0u8; 2];
+        reader.read_exact(&mut v)?; // data_reference_index
+        let base_offset = read_n(reader, base_offset_size)?;
+        let mut ec = [0u8; 2];
+        reader.read_exact(&mut ec)?;
+        let extent_count = u16::from_be_bytes(ec);
+        for _ in 0..extent_count {
+            if index_size != 0 {
+                read_n(reader, index_size)?;
+            }
+            let extent_offset = read_n(reader, offset_size)?;
+            let _extent_length = read_n(reader, length_size)?;
+            if cur_item_id != item_id && base_offset + extent_offset >= boundary {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
+
+/// Extracts the raw Exif (TIFF) data embedded as an `Exif` item in an
+/// ISOBMFF (HEIF/HEIC/AVIF) container.
+pub fn get_exif_attr<R>(reader: &mut R) -> Result<Vec<u8>, Error>
+where
+    R: Read + Seek,
+{
+    let (offset, length) = find_exif_extent(reader)?;
+    // The item payload is a 4-byte big-endian offset to the TIFF
+    // header (normally 0) followed by the TIFF data itself
+    // [ISO/IEC 23008-12 Annex A].
+    reader.seek(SeekFrom::Start(offset))?;
+    let mut hdr_offset = [0u8; 4];
+    reader.read_exact(&mut hdr_offset)?;
+    let skip = u32::from_be_bytes(hdr_offset) as u64;
+    let data_len = length
+        .checked_sub(4)
+        .and_then(|n| n.checked_sub(skip))
+        .ok_or(Error::InvalidFormat("Exif item length too small for TIFF header offset"))?;
+    reader.seek(SeekFrom::Start(offset + 4 + skip))?;
+    let mut data = vec![0u8; data_len as usize];
+    reader.read_exact(&mut data)?;
+    Ok(data)
+}
+
+/// Returns a new ISOBMFF byte stream with `exif_data` spliced in place
+/// of the existing `Exif` item's extent, patching the enclosing box's
+/// size.
+///
+/// This only supports the common layout that encoders actually produce:
+/// a single-extent `Exif` item located with `construction_method == 0`,
+/// and no other `iloc` item with an extent that lies after the replaced
+/// one (such an extent's offset would need shifting by the size delta,
+/// which this function does not do). Anything else, including that
+/// case, is reported as `Error::InvalidFormat` rather than risking a
+/// corrupted container.
+pub fn set_exif_attr<R>(reader: &mut R, exif_data: &[u8]) -> Result<Vec<u8>, Error>
+where
+    R: Read + Seek,
+{
+    let (old_offset, old_length) = find_exif_extent(reader)?;
+    reader.seek(SeekFrom::Start(old_offset))?;
+    let mut hdr_offset = [0u8; 4];
+    reader.read_exact(&mut hdr_offset)?;
+    let skip = u32::from_be_bytes(hdr_offset) as u64;
+
+    let mut new_payload = Vec::with_capacity(4 + exif_data.len());
+    new_payload.extend_from_slice(&(skip as u32).to_be_bytes());
+    new_payload.extend_from_slice(&vec![0u8; skip as usize]);
+    new_payload.extend_from_slice(exif_data);
+    let delta = new_payload.len() as i64 - old_length as i64;
+    let old_end = old_offset + old_length;
+
+    if delta != 0 {
+        let (item_id, iloc) = find_exif_item_and_iloc(reader)?;
+        if other_item_has_extent_after(reader, &iloc, item_id, old_end)? {
+            return Err(Error::InvalidFormat(
+                "Replacing the Exif item would shift other iloc item extents; not supported",
+            ));
+        }
+    }
+
+    reader.seek(SeekFrom::Start(0))?;
+    let mut file = Vec::new();
+    reader.read_to_end(&mut file)?;
+
+    let mut out = Vec::with_capacity(file.len().saturating_add(delta.unsigned_abs() as usize));
+    out.extend_from_slice(&file[..old_offset as usize]);
+    out.extend_from_slice(&new_payload);
+    out.extend_from_slice(&file[old_end as usize..]);
+
+    // Patch the size of the top-level box (typically `mdat`) that
+    // contains the replaced extent.
+    let top = read_boxes(&mut std::io::Cursor::new(&file), 0, file.len() as u64)?;
+    let containing = top
+        .iter()
+        .find(|b| old_offset >= b.payload_start && old_end <= b.payload_start + b.payload_len)
+        .ok_or(Error::InvalidFormat(
+            "Exif extent is not contained in a single top-level box",
+        ))?;
+    patch_box_size(&mut out, containing.start, delta)?;
+    if containing.typ != MDAT {
+        // An Exif extent outside `mdat` is not a layout this function
+        // supports, so it is reported as an error.
+        return Err(Error::InvalidFormat(
+            "Exif extent unexpectedly lives outside mdat",
+        ));
+    }
+
+    Ok(out)
+}
+
+fn patch_box_size(buf: &mut [u8], box_start: u64, delta: i64) -> Result<(), Error> {
+    let start = box_start as usize;
+    let size32 = u32::from_be_bytes(buf[start..start + 4].try_into().unwrap());
+    if size32 == 1 {
+        let old = u64::from_be_bytes(buf[start + 8..start + 16].try_into().unwrap());
+        let new = (old as i64 + delta) as u64;
+        buf[start + 8..start + 16].copy_from_slice(&new.to_be_bytes());
+    } else if size32 == 0 {
+        // Size extends to EOF; nothing to patch.
+    } else {
+        let new = (size32 as i64 + delta) as u32;
+        buf[start..start + 4].copy_from_slice(&new.to_be_bytes());
+    }
+    Ok(())
+}
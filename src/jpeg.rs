@@ -0,0 +1,122 @@
+use std::io::Read;
+
+use crate::Error;
+
+// The Exif APP1 segment data is prefixed with this identifier before the
+// TIFF header actually starts [JEITA CP-3451 Section 3.6.3].
+const EXIF_MARKER: &[u8] = b"Exif\0\0";
+
+// JPEG markers that are not followed by a 2-byte length field.
+fn is_standalone(marker: u8) -> bool {
+    marker == 0x01 || (0xd0..=0xd9).contains(&marker)
+}
+
+pub fn is_jpeg(buf: &[u8]) -> bool {
+    buf.len() >= 2 && buf[0] == 0xff && buf[1] == 0xd8
+}
+
+/// Extracts the raw Exif (TIFF) data embedded in a JPEG's APP1 segment.
+pub fn get_exif_attr<R>(reader: &mut R) -> Result<Vec<u8>, Error>
+where
+    R: Read,
+{
+    let (segs, _trailer) = read_segments(reader)?;
+    for seg in &segs {
+        if seg.marker == 0xe1 && seg.data.starts_with(EXIF_MARKER) {
+            return Ok(seg.data[EXIF_MARKER.len()..].to_vec());
+        }
+    }
+    Err(Error::NotFound("Exif APP1 segment not found in JPEG"))
+}
+
+/// Returns a new JPEG byte stream with `exif_data` (a raw TIFF blob, as
+/// produced by `exif::experimental::Writer` or accepted by
+/// `Reader::read_raw`) embedded as the APP1 Exif segment, replacing any
+/// existing one.
+///
+/// All other segments, and the entropy-coded scan data following SOS,
+/// are preserved byte for byte.
+pub fn set_exif_attr(jpeg_data: &[u8], exif_data: &[u8]) -> Result<Vec<u8>, Error> {
+    if !is_jpeg(jpeg_data) {
+        return Err(Error::InvalidFormat("Not a JPEG file"));
+    }
+    if exif_data.len() > 0xffff - 2 - EXIF_MARKER.len() {
+        return Err(Error::InvalidFormat(
+            "Exif data too large for a JPEG segment",
+        ));
+    }
+
+    let (mut segs, trailer) = read_segments(&mut &jpeg_data[2..])?;
+    segs.retain(|seg| !(seg.marker == 0xe1 && seg.data.starts_with(EXIF_MARKER)));
+
+    let mut exif_seg_data = Vec::with_capacity(EXIF_MARKER.len() + exif_data.len());
+    exif_seg_data.extend_from_slice(EXIF_MARKER);
+    exif_seg_data.extend_from_slice(exif_data);
+    let exif_seg = Segment {
+        marker: 0xe1,
+        data: exif_seg_data,
+    };
+    // Insert right after any leading APP0 (JFIF) segment, or at the
+    // very front of the segment list otherwise.
+    let insert_at = usize::from(segs.first().map_or(false, |s| s.marker == 0xe0));
+    segs.insert(insert_at, exif_seg);
+
+    let mut out = Vec::with_capacity(jpeg_data.len() + exif_data.len() + EXIF_MARKER.len());
+    out.extend_from_slice(&[0xff, 0xd8]);
+    for seg in &segs {
+        out.push(0xff);
+        out.push(seg.marker);
+        if !is_standalone(seg.marker) {
+            let len = seg.data.len() + 2;
+            out.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        out.extend_from_slice(&seg.data);
+    }
+    out.extend_from_slice(&trailer);
+    Ok(out)
+}
+
+struct Segment {
+    marker: u8,
+    data: Vec<u8>,
+}
+
+// Reads the marker segments up to (and not including) SOS, returning
+// them along with the untouched trailer: the SOS marker itself, the
+// entropy-coded scan data, and everything up to EOI.
+fn read_segments<R>(reader: &mut R) -> Result<(Vec<Segment>, Vec<u8>), Error>
+where
+    R: Read,
+{
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf)?;
+    let mut pos = 0;
+    let mut segs = Vec::new();
+    while pos + 1 < buf.len() {
+        if buf[pos] != 0xff {
+            return Err(Error::InvalidFormat("Expected a JPEG marker"));
+        }
+        let marker = buf[pos + 1];
+        if marker == 0xd9 || marker == 0xda {
+            // EOI, or Start of Scan: stop parsing and hand back
+            // everything from here on as an opaque trailer.
+            return Ok((segs, buf[pos..].to_vec()));
+        }
+        pos += 2;
+        if is_standalone(marker) {
+            segs.push(Segment { marker, data: Vec::new() });
+            continue;
+        }
+        if pos + 2 > buf.len() {
+            return Err(Error::InvalidFormat("Truncated JPEG segment length"));
+        }
+        let len = u16::from_be_bytes([buf[pos], buf[pos + 1]]) as usize;
+        if len < 2 || pos + len > buf.len() {
+            return Err(Error::InvalidFormat("Truncated JPEG segment"));
+        }
+        let data = buf[pos + 2..pos + len].to_vec();
+        segs.push(Segment { marker, data });
+        pos += len;
+    }
+    Ok((segs, Vec::new()))
+}
@@ -24,6 +24,16 @@
 // SUCH DAMAGE.
 //
 
+// The `std` feature (on by default) pulls in the container readers/
+// writers, which need `std::io`.  With `std` off, only the `core`+
+// `alloc` parsing primitives (`Field`, `Value`, `DateTime`, ...) are
+// available, for embedded targets that want to pull timestamps and
+// field values out of a buffer without linking libstd.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(not(feature = "std"))]
+extern crate alloc;
+
 //! This is a pure-Rust library to parse Exif data.
 //!
 //! This library parses Exif attributes in a raw Exif data block.
@@ -101,9 +111,12 @@
 //! The use cases include caching getter and delayed evaluation.
 
 pub use error::Error;
+#[cfg(feature = "std")]
 pub use exif::Exif;
 pub use ifd::{DateTime, Field, In};
+#[cfg(feature = "std")]
 pub use jpeg::get_exif_attr as get_exif_attr_from_jpeg;
+#[cfg(feature = "std")]
 pub use reader::Reader;
 pub use tag::{Context, Tag};
 pub use tiff::parse_exif;
@@ -111,7 +124,9 @@ pub use value::Value;
 pub use value::{Rational, SRational};
 
 /// The interfaces in this module are experimental and unstable.
+#[cfg(feature = "std")]
 pub mod experimental {
+    pub use crate::lazy::LazyExif;
     pub use crate::writer::Writer;
 }
 
@@ -122,21 +137,32 @@ mod tmacro;
 pub mod doc;
 mod endian;
 mod error;
+#[cfg(feature = "std")]
 pub mod exif;
 pub mod ifd;
+#[cfg(feature = "std")]
 mod isobmff;
+#[cfg(feature = "std")]
 mod jpeg;
+#[cfg(feature = "std")]
+mod lazy;
+mod makernote;
 mod parser;
+#[cfg(feature = "std")]
 mod png;
+#[cfg(feature = "std")]
 mod reader;
 #[macro_use]
 mod tag;
 
+#[cfg(feature = "std")]
 mod fuji;
 mod tiff;
 mod util;
 mod value;
+#[cfg(feature = "std")]
 mod webp;
+#[cfg(feature = "std")]
 mod writer;
 
 //
@@ -165,8 +191,19 @@ mod writer;
 // SUCH DAMAGE.
 //
 
+#[cfg(feature = "std")]
 use std::cell::{Cell, UnsafeCell};
+#[cfg(feature = "std")]
 use std::ops::{Deref, DerefMut, Drop};
+#[cfg(not(feature = "std"))]
+use core::cell::{Cell, UnsafeCell};
+#[cfg(not(feature = "std"))]
+use core::ops::{Deref, DerefMut, Drop};
+use core::mem;
+#[cfg(feature = "std")]
+use std::sync::atomic::{AtomicU8, Ordering};
+#[cfg(not(feature = "std"))]
+use core::sync::atomic::{AtomicU8, Ordering};
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 enum State {
@@ -175,6 +212,34 @@ enum State {
     Fixed,
 }
 
+/// The error returned by `MutOnce::try_get_mut` and
+/// `MutOnce::try_get_ref` in exactly the cases where `get_mut` and
+/// `get_ref` would otherwise panic.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BorrowError {
+    /// `try_get_mut` was called while another `RefMut` was still alive.
+    AlreadyMutablyBorrowed,
+    /// `try_get_mut` was called after the value was fixed by `get_ref`
+    /// or `try_get_ref`.
+    NoLongerMutable,
+    /// `try_get_ref` was called while a `RefMut` was still alive.
+    StillMutablyBorrowed,
+}
+
+#[cfg(feature = "std")]
+impl std::fmt::Display for BorrowError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            BorrowError::AlreadyMutablyBorrowed => "already mutably borrowed",
+            BorrowError::NoLongerMutable => "no longer mutable",
+            BorrowError::StillMutablyBorrowed => "still mutably borrowed",
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BorrowError {}
+
 /// A mutable memory location that is write-once and can be borrowed as
 /// plain `&T`.
 ///
@@ -321,6 +386,57 @@ impl<T> MutOnce<T> {
         unsafe { &*self.value.get() }
     }
 
+    /// Mutably borrows the wrapped value, like `get_mut`, but returns
+    /// a `BorrowError` instead of panicking if the value is currently
+    /// mutably borrowed or has already been fixed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mutate_once::{BorrowError, MutOnce};
+    /// let mo = MutOnce::new(0);
+    /// *mo.try_get_mut().unwrap() += 7;
+    /// assert_eq!(*mo.get_ref(), 7);
+    /// assert_eq!(mo.try_get_mut().unwrap_err(), BorrowError::NoLongerMutable);
+    /// ```
+    #[inline]
+    pub fn try_get_mut(&self) -> Result<RefMut<T>, BorrowError> {
+        match self.state.get() {
+            State::Unborrowed => {
+                self.state.replace(State::Updating);
+                Ok(RefMut { target: self })
+            }
+            State::Updating => Err(BorrowError::AlreadyMutablyBorrowed),
+            State::Fixed => Err(BorrowError::NoLongerMutable),
+        }
+    }
+
+    /// Returns an immutable reference to the value, like `get_ref`,
+    /// but returns a `BorrowError` instead of panicking if the value
+    /// is currently mutably borrowed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mutate_once::{BorrowError, MutOnce};
+    /// let mo = MutOnce::new(0);
+    /// let mut_ref = mo.get_mut();
+    /// assert_eq!(mo.try_get_ref(), Err(BorrowError::StillMutablyBorrowed));
+    /// drop(mut_ref);
+    /// assert_eq!(mo.try_get_ref(), Ok(&0));
+    /// ```
+    #[inline]
+    pub fn try_get_ref(&self) -> Result<&T, BorrowError> {
+        match self.state.get() {
+            State::Unborrowed => {
+                self.state.replace(State::Fixed);
+            }
+            State::Updating => return Err(BorrowError::StillMutablyBorrowed),
+            State::Fixed => {}
+        }
+        Ok(unsafe { &*self.value.get() })
+    }
+
     /// Returns true if the value can be no longer mutated (in other words,
     /// if `get_ref` is ever called).
     #[inline]
@@ -328,6 +444,68 @@ impl<T> MutOnce<T> {
         self.state.get() == State::Fixed
     }
 
+    /// Returns a fixed reference to the value, calling `f` to produce
+    /// its replacement first if it isn't fixed yet.
+    ///
+    /// Collapses the `if !is_fixed() { *get_mut() = ...; } get_ref()`
+    /// idiom used by this crate's caching getters into one call that
+    /// can't accidentally call `get_mut` after the value is fixed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is currently mutably borrowed by an active
+    /// `RefMut`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mo = mutate_once::MutOnce::new(String::new());
+    /// assert_eq!(mo.get_ref_or_init(|| "expensive".to_string()), "expensive");
+    /// // Already fixed, so `f` is not called again.
+    /// assert_eq!(mo.get_ref_or_init(|| "ignored".to_string()), "expensive");
+    /// ```
+    #[inline]
+    pub fn get_ref_or_init<F>(&self, f: F) -> &T
+    where
+        F: FnOnce() -> T,
+    {
+        if !self.is_fixed() {
+            *self.get_mut() = f();
+        }
+        self.get_ref()
+    }
+
+    /// Returns a fixed reference to the value, calling `f` to modify
+    /// it in place first if it isn't fixed yet.
+    ///
+    /// Like `get_ref_or_init`, but for the in-place case: appending to
+    /// a pre-seeded `String`/`Vec` rather than replacing the whole
+    /// value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is currently mutably borrowed by an active
+    /// `RefMut`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mo = mutate_once::MutOnce::new(Vec::new());
+    /// assert_eq!(mo.get_ref_or_modify(|v| v.push(1)), &[1]);
+    /// // Already fixed, so `f` is not called again.
+    /// assert_eq!(mo.get_ref_or_modify(|v| v.push(2)), &[1]);
+    /// ```
+    #[inline]
+    pub fn get_ref_or_modify<F>(&self, f: F) -> &T
+    where
+        F: FnOnce(&mut T),
+    {
+        if !self.is_fixed() {
+            f(&mut self.get_mut());
+        }
+        self.get_ref()
+    }
+
     /// Consumes the `MutOnce`, returning the wrapped value.
     #[inline]
     pub fn into_inner(self) -> T {
@@ -379,6 +557,234 @@ impl<'a, T> Drop for RefMut<'a, T> {
     }
 }
 
+impl<'a, T> RefMut<'a, T> {
+    /// Narrows a `RefMut<T>` to a mutable borrow of one of `T`'s
+    /// components, consuming the original guard.
+    ///
+    /// The returned `MappedRefMut` keeps the original `MutOnce<T>`
+    /// borrowed for as long as it is alive, and resets its state back
+    /// to `Unborrowed` on drop exactly like the original `RefMut`
+    /// would have. This lets code that caches a composite struct in
+    /// a single `MutOnce` (e.g. a decoded-IFD cache) hand out a
+    /// mutable borrow of just one field without exposing the whole
+    /// value or cloning it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mutate_once::{MutOnce, RefMut};
+    /// let mo = MutOnce::new((0, Vec::<i32>::new()));
+    /// {
+    ///     let mut elems = RefMut::map(mo.get_mut(), |pair| &mut pair.1);
+    ///     elems.push(1);
+    ///     elems.push(2);
+    /// }
+    /// assert_eq!(mo.get_ref().1, [1, 2]);
+    /// ```
+    #[inline]
+    pub fn map<U, F>(orig: RefMut<'a, T>, f: F) -> MappedRefMut<'a, T, U>
+    where
+        F: FnOnce(&mut T) -> &mut U,
+    {
+        let target = orig.target;
+        // Safety: `orig` guarantees exclusive access to the value for
+        // as long as `target`'s state stays `Updating`; `MappedRefMut`
+        // below inherits that guarantee and releases it on drop
+        // exactly like `orig` would have, so forgetting `orig`
+        // without running its `Drop` is sound.
+        let value: *mut U = f(unsafe { &mut *target.value.get() });
+        mem::forget(orig);
+        MappedRefMut { target, value }
+    }
+}
+
+/// A wrapper type for a mutable borrow of a component of a
+/// `MutOnce<T>`'s value, produced by `RefMut::map`.
+pub struct MappedRefMut<'a, T, U> {
+    target: &'a MutOnce<T>,
+    value: *mut U,
+}
+
+impl<'a, T, U> Deref for MappedRefMut<'a, T, U> {
+    type Target = U;
+
+    #[inline]
+    fn deref(&self) -> &U {
+        unsafe { &*self.value }
+    }
+}
+
+impl<'a, T, U> DerefMut for MappedRefMut<'a, T, U> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut U {
+        unsafe { &mut *self.value }
+    }
+}
+
+impl<'a, T, U> Drop for MappedRefMut<'a, T, U> {
+    #[inline]
+    fn drop(&mut self) {
+        debug_assert_eq!(self.target.state.get(), State::Updating);
+        self.target.state.replace(State::Unborrowed);
+    }
+}
+
+const SYNC_UNBORROWED: u8 = 0;
+const SYNC_UPDATING: u8 = 1;
+const SYNC_FIXED: u8 = 2;
+
+/// A thread-safe sibling of `MutOnce`.
+///
+/// `MutOnce`'s `Cell<State>` makes it `!Sync`, so a value cached in it
+/// can't be shared across threads even though the write-once protocol
+/// would allow it. `SyncMutOnce` replaces the `Cell` with an
+/// `AtomicU8` and is `Sync` whenever `T` is, so e.g. a lazily-decoded
+/// Exif field cache can live behind an `Arc` in a multithreaded
+/// pipeline.
+///
+/// The transitions are the same as `MutOnce`'s, performed with
+/// compare-and-swap instead of a plain `Cell` replace: `get_mut` CASes
+/// `Unborrowed -> Updating`, and `get_ref` CASes `Unborrowed ->
+/// Fixed`. `RefMut`'s drop publishes the `Updating` phase's writes
+/// with a `Release` store back to `Unborrowed`; `get_ref`'s CAS reads
+/// with `Acquire` on both success and failure, so once the state is
+/// observed as `Fixed`, every write made while it was `Updating` is
+/// guaranteed visible before the value is handed out as `&T`.
+#[derive(Debug)]
+pub struct SyncMutOnce<T> {
+    value: UnsafeCell<T>,
+    state: AtomicU8,
+}
+
+// `UnsafeCell<T>` is `!Sync` regardless of `T`; the write-once
+// protocol above is what makes it sound to share a `SyncMutOnce<T>`
+// across threads once `T` itself is `Sync`.
+unsafe impl<T: Sync> Sync for SyncMutOnce<T> {}
+
+impl<T> SyncMutOnce<T> {
+    /// Creates a new `SyncMutOnce` containing the given `value`.
+    #[inline]
+    pub const fn new(value: T) -> Self {
+        Self {
+            value: UnsafeCell::new(value),
+            state: AtomicU8::new(SYNC_UNBORROWED),
+        }
+    }
+
+    /// Mutably borrows the wrapped value.
+    ///
+    /// The borrow lasts until the returned `SyncRefMut` gets dropped.
+    /// This method must not be called if another `SyncRefMut` is
+    /// active or `get_ref` is ever called.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is currently mutably borrowed or ever
+    /// immutably borrowed.
+    #[inline]
+    pub fn get_mut(&self) -> SyncRefMut<T> {
+        match self.state.compare_exchange(
+            SYNC_UNBORROWED,
+            SYNC_UPDATING,
+            Ordering::Acquire,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => SyncRefMut { target: self },
+            Err(SYNC_UPDATING) => panic!("already mutably borrowed"),
+            Err(_) => panic!("no longer mutable"),
+        }
+    }
+
+    /// Returns an immutable reference to the value.
+    ///
+    /// This method must not be called while the value is mutably
+    /// borrowed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is currently mutably borrowed.
+    #[inline]
+    pub fn get_ref(&self) -> &T {
+        match self.state.compare_exchange(
+            SYNC_UNBORROWED,
+            SYNC_FIXED,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        ) {
+            Ok(_) | Err(SYNC_FIXED) => {}
+            Err(SYNC_UPDATING) => panic!("still mutably borrowed"),
+            Err(_) => unreachable!("SyncMutOnce state is always Unborrowed, Updating, or Fixed"),
+        }
+        // Safety: reaching here means the state is `Fixed`, either
+        // just published by the `AcqRel` CAS above or observed with
+        // `Acquire` on a prior thread's `Release` store, either of
+        // which happens-after every write made while the state was
+        // `Updating`. No further mutable borrow is possible once
+        // `Fixed` is reached, so handing out `&T` is sound.
+        unsafe { &*self.value.get() }
+    }
+
+    /// Returns true if the value can be no longer mutated (in other words,
+    /// if `get_ref` is ever called).
+    #[inline]
+    pub fn is_fixed(&self) -> bool {
+        self.state.load(Ordering::Acquire) == SYNC_FIXED
+    }
+
+    /// Consumes the `SyncMutOnce`, returning the wrapped value.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+
+impl<T: Default> Default for SyncMutOnce<T> {
+    #[inline]
+    fn default() -> SyncMutOnce<T> {
+        SyncMutOnce::new(T::default())
+    }
+}
+
+impl<T> From<T> for SyncMutOnce<T> {
+    #[inline]
+    fn from(t: T) -> SyncMutOnce<T> {
+        SyncMutOnce::new(t)
+    }
+}
+
+/// A wrapper type for a mutably borrowed value from a `SyncMutOnce<T>`.
+#[derive(Debug)]
+pub struct SyncRefMut<'a, T> {
+    target: &'a SyncMutOnce<T>,
+}
+
+impl<'a, T> Deref for SyncRefMut<'a, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.target.value.get() }
+    }
+}
+
+impl<'a, T> DerefMut for SyncRefMut<'a, T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.target.value.get() }
+    }
+}
+
+impl<'a, T> Drop for SyncRefMut<'a, T> {
+    #[inline]
+    fn drop(&mut self) {
+        debug_assert_eq!(
+            self.target.state.load(Ordering::Relaxed),
+            SYNC_UPDATING
+        );
+        self.target.state.store(SYNC_UNBORROWED, Ordering::Release);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -477,4 +883,146 @@ mod tests {
         *mo.get_mut() += 9;
         assert_eq!(*mo.get_ref(), 9);
     }
+
+    #[test]
+    fn try_get_mut_succeeds_when_unborrowed() {
+        let mo = MutOnce::new(Vec::new());
+        *mo.try_get_mut().unwrap() = vec![1, 2];
+        assert_eq!(mo.get_ref(), &[1, 2]);
+    }
+
+    #[test]
+    fn try_get_mut_after_ref() {
+        let mo = MutOnce::new(0);
+        mo.get_ref();
+        assert_eq!(mo.try_get_mut().unwrap_err(), BorrowError::NoLongerMutable);
+    }
+
+    #[test]
+    fn try_get_mut_while_mut() {
+        let mo = MutOnce::new(0);
+        let _mutref = mo.get_mut();
+        assert_eq!(
+            mo.try_get_mut().unwrap_err(),
+            BorrowError::AlreadyMutablyBorrowed
+        );
+    }
+
+    #[test]
+    fn try_get_ref_succeeds_when_unborrowed() {
+        let mo = MutOnce::new(0);
+        *mo.get_mut() += 3;
+        assert_eq!(mo.try_get_ref(), Ok(&3));
+    }
+
+    #[test]
+    fn try_get_ref_while_mut() {
+        let mo = MutOnce::new(0);
+        let _mutref = mo.get_mut();
+        assert_eq!(mo.try_get_ref(), Err(BorrowError::StillMutablyBorrowed));
+    }
+
+    #[test]
+    fn sync_repeated_muts() {
+        let mo = SyncMutOnce::new(Vec::new());
+        {
+            let mut mutvec = mo.get_mut();
+            mutvec.push(1);
+            mutvec.push(2);
+        }
+        {
+            let mut mutvec = mo.get_mut();
+            mutvec.push(3);
+        }
+        assert_eq!(mo.get_ref(), &[1, 2, 3]);
+    }
+
+    #[test]
+    #[should_panic(expected = "still mutably borrowed")]
+    fn sync_ref_while_mut() {
+        let mo = SyncMutOnce::new(0);
+        let mut mutref = mo.get_mut();
+        *mutref += 1;
+        mo.get_ref();
+    }
+
+    #[test]
+    #[should_panic(expected = "no longer mutable")]
+    fn sync_mut_after_ref() {
+        let mo = SyncMutOnce::new(0);
+        assert_eq!(*mo.get_ref(), 0);
+        mo.get_mut();
+    }
+
+    #[test]
+    #[should_panic(expected = "already mutably borrowed")]
+    fn sync_multiple_muts() {
+        let mo = SyncMutOnce::new(0);
+        let _mutref1 = mo.get_mut();
+        mo.get_mut();
+    }
+
+    #[test]
+    fn sync_is_fixed() {
+        let mo = SyncMutOnce::new(0);
+        assert!(!mo.is_fixed());
+        mo.get_ref();
+        assert!(mo.is_fixed());
+    }
+
+    #[test]
+    fn ref_mut_map_projects_a_component() {
+        let mo = MutOnce::new((0, Vec::<i32>::new()));
+        {
+            let mut elems = RefMut::map(mo.get_mut(), |pair| &mut pair.1);
+            elems.push(1);
+            elems.push(2);
+        }
+        // The original `MutOnce` is usable again: `map` released its
+        // borrow on drop just like an unmapped `RefMut` would have.
+        mo.get_mut().0 = 9;
+        assert_eq!(*mo.get_ref(), (9, vec![1, 2]));
+    }
+
+    #[test]
+    #[should_panic(expected = "already mutably borrowed")]
+    fn ref_mut_map_keeps_original_borrowed() {
+        let mo = MutOnce::new((0, 0));
+        let _mapped = RefMut::map(mo.get_mut(), |pair| &mut pair.1);
+        mo.get_mut();
+    }
+
+    #[test]
+    fn get_ref_or_init_runs_once() {
+        let mo = MutOnce::new(0);
+        assert_eq!(*mo.get_ref_or_init(|| 7), 7);
+        assert_eq!(*mo.get_ref_or_init(|| 9), 7);
+    }
+
+    #[test]
+    fn get_ref_or_modify_runs_once() {
+        let mo = MutOnce::new(Vec::new());
+        assert_eq!(mo.get_ref_or_modify(|v| v.push(1)), &[1]);
+        assert_eq!(mo.get_ref_or_modify(|v| v.push(2)), &[1]);
+    }
+
+    #[test]
+    fn sync_across_threads() {
+        use std::sync::Arc;
+
+        let mo = Arc::new(SyncMutOnce::new(0));
+        {
+            let mo = Arc::clone(&mo);
+            std::thread::spawn(move || {
+                *mo.get_mut() = 42;
+            })
+            .join()
+            .unwrap();
+        }
+        let mo = Arc::clone(&mo);
+        let got = std::thread::spawn(move || *mo.get_ref())
+            .join()
+            .unwrap();
+        assert_eq!(got, 42);
+    }
 }
@@ -0,0 +1,244 @@
+//! Vendor-specific `MakerNote` sub-IFD decoding.
+//!
+//! The `MakerNote` tag (Exif 0x927c) is, by convention, a TIFF-like IFD
+//! embedded in the field's value, but its signature, byte order, and
+//! the base that its internal offsets are relative to are all
+//! vendor-specific. This module detects the vendor from the leading
+//! signature and parses the embedded IFD into ordinary `IfdEntry`s
+//! under `Context::MakerNote`, so `Exif::get_field` can reach vendor
+//! tags the same way it reaches standard ones.
+
+use crate::endian::{BigEndian, Endian, LittleEndian};
+use crate::error::Error;
+use crate::ifd::{Field, IfdEntry};
+use crate::tag::{Context, Tag};
+use crate::value::{get_type_info, Value};
+use crate::In;
+
+// The base that a vendor's MakerNote IFD entries use for the
+// value-or-offset field of values larger than 4 bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OffsetBase {
+    // Offsets are relative to the start of the whole TIFF header,
+    // exactly like any other IFD (Olympus, Sony).
+    TiffHeader,
+    // Offsets are relative to the start of the MakerNote value itself
+    // (Canon and most headerless makers, plus Fujifilm's JPEG
+    // MakerNote, as opposed to the unrelated RAF container format
+    // handled by `crate::fuji`).
+    MakerNoteStart,
+    // Offsets are relative to the start of the vendor's own embedded
+    // TIFF header, i.e. `value_offset + header_len` (Nikon type 2:
+    // its IFD offset and every external value inside that IFD are
+    // both resolved against the byte-order mark read just after
+    // `header_len`, not against the MakerNote value's own start).
+    EmbeddedTiffHeader,
+}
+
+struct VendorFormat {
+    // Bytes to skip at the start of the MakerNote value before the
+    // IFD (or, for Nikon, its own embedded TIFF header) begins.
+    header_len: usize,
+    // If true, a 2-byte byte-order mark plus a 4-byte IFD offset
+    // (exactly like a TIFF header) follow `header_len` and override
+    // the outer TIFF's byte order and IFD location.
+    byte_order_follows: bool,
+    offset_base: OffsetBase,
+}
+
+fn detect_vendor(note: &[u8]) -> VendorFormat {
+    if note.starts_with(b"Nikon\0") {
+        VendorFormat {
+            header_len: 10,
+            byte_order_follows: true,
+            offset_base: OffsetBase::EmbeddedTiffHeader,
+        }
+    } else if note.starts_with(b"SONY DSC \0") || note.starts_with(b"SONY CAM \0") {
+        VendorFormat {
+            header_len: 12,
+            byte_order_follows: false,
+            offset_base: OffsetBase::TiffHeader,
+        }
+    } else if note.starts_with(b"OLYMPUS\0II") || note.starts_with(b"OLYMPUS\0MM") {
+        VendorFormat {
+            header_len: 12,
+            byte_order_follows: false,
+            offset_base: OffsetBase::TiffHeader,
+        }
+    } else if note.starts_with(b"OLYMP\0") {
+        VendorFormat {
+            header_len: 8,
+            byte_order_follows: false,
+            offset_base: OffsetBase::TiffHeader,
+        }
+    } else if note.starts_with(b"FUJIFILM") {
+        VendorFormat {
+            header_len: 12,
+            byte_order_follows: false,
+            offset_base: OffsetBase::MakerNoteStart,
+        }
+    } else {
+        // Canon, and most other makers without a signature: a
+        // headerless IFD in the outer TIFF's byte order, with
+        // external values relative to the MakerNote's own start.
+        VendorFormat {
+            header_len: 0,
+            byte_order_follows: false,
+            offset_base: OffsetBase::MakerNoteStart,
+        }
+    }
+}
+
+/// Parses the vendor-specific IFD embedded in a `MakerNote` field's
+/// value and returns it as `IfdEntry`s under `Context::MakerNote`.
+///
+/// `data` is the whole TIFF buffer, so that the returned entries can
+/// be resolved lazily through the ordinary `IfdEntry`/`Value::Unknown`
+/// machinery exactly like any other IFD; `value_offset` and
+/// `value_len` locate the raw `MakerNote` bytes within it.
+pub fn parse(
+    data: &[u8],
+    value_offset: usize,
+    value_len: usize,
+    outer_le: bool,
+    ifd_num: u16,
+) -> Result<Vec<IfdEntry>, Error> {
+    if data.len() < value_offset || data.len() - value_offset < value_len {
+        return Err(Error::InvalidFormat("Truncated MakerNote"));
+    }
+    let note = &data[value_offset..value_offset + value_len];
+    let vendor = detect_vendor(note);
+    if vendor.header_len > note.len() {
+        return Err(Error::InvalidFormat("Truncated MakerNote header"));
+    }
+
+    let mut le = outer_le;
+    let mut ifd_offset = value_offset + vendor.header_len;
+    if vendor.byte_order_follows {
+        if note.len() < vendor.header_len + 8 {
+            return Err(Error::InvalidFormat("Truncated MakerNote TIFF header"));
+        }
+        le = match BigEndian::loadu16(note, vendor.header_len) {
+            0x4949 => true,
+            0x4d4d => false,
+            _ => return Err(Error::InvalidFormat("Invalid MakerNote byte order")),
+        };
+        let rel_ifd_offset = (if le {
+            LittleEndian::loadu32(note, vendor.header_len + 4)
+        } else {
+            BigEndian::loadu32(note, vendor.header_len + 4)
+        }) as usize;
+        // Nikon type 2's IFD offset is relative to its own embedded
+        // TIFF header, i.e. to the byte-order mark read just above.
+        ifd_offset = value_offset + vendor.header_len + rel_ifd_offset;
+    }
+
+    let base = match vendor.offset_base {
+        OffsetBase::TiffHeader => 0,
+        OffsetBase::MakerNoteStart => value_offset,
+        // Matches the `ifd_offset` computation above: both the IFD
+        // location and the values inside it are relative to the
+        // embedded TIFF header, not to the MakerNote value's start.
+        OffsetBase::EmbeddedTiffHeader => value_offset + vendor.header_len,
+    };
+
+    let mut entries = Vec::new();
+    if le {
+        parse_ifd::<LittleEndian>(data, ifd_offset, base, ifd_num, &mut entries)?;
+    } else {
+        parse_ifd::<BigEndian>(data, ifd_offset, base, ifd_num, &mut entries)?;
+    }
+    Ok(entries)
+}
+
+fn parse_ifd<E>(
+    data: &[u8],
+    offset: usize,
+    base: usize,
+    ifd_num: u16,
+    entries: &mut Vec<IfdEntry>,
+) -> Result<(), Error>
+where
+    E: Endian,
+{
+    if data.len() < offset || data.len() - offset < 2 {
+        return Err(Error::InvalidFormat("Truncated MakerNote IFD count"));
+    }
+    let count = E::loadu16(data, offset) as usize;
+    if data.len() - offset - 2 < count * 12 {
+        return Err(Error::InvalidFormat("Truncated MakerNote IFD"));
+    }
+    for i in 0..count {
+        let entry_offset = offset + 2 + i * 12;
+        let tagnum = E::loadu16(data, entry_offset);
+        let typ = E::loadu16(data, entry_offset + 2);
+        let cnt = E::loadu32(data, entry_offset + 4);
+        let valofs_at = entry_offset + 8;
+        let (unitlen, _parser) = get_type_info::<E>(typ);
+        let vallen = unitlen
+            .checked_mul(cnt as usize)
+            .ok_or(Error::InvalidFormat("Invalid MakerNote entry count"))?;
+        let value = if unitlen == 0 || vallen <= 4 {
+            Value::Unknown(typ, cnt, valofs_at as u32)
+        } else {
+            let rel_ofs = E::loadu32(data, valofs_at) as usize;
+            let ofs = base + rel_ofs;
+            if data.len() < ofs || data.len() - ofs < vallen {
+                return Err(Error::InvalidFormat("Truncated MakerNote field value"));
+            }
+            Value::Unknown(typ, cnt, ofs as u32)
+        };
+        entries.push(IfdEntry {
+            field: Field {
+                tag: Tag(Context::MakerNote, tagnum),
+                ifd_num: In(ifd_num),
+                value,
+            }
+            .into(),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A Nikon type 2 MakerNote: "Nikon\0" + 4 bytes of version/unknown
+    // fields (header_len == 10), followed by its own embedded TIFF
+    // header (byte-order mark, 0x002a, and an IFD offset relative to
+    // that header) and a one-entry IFD whose single field is an
+    // 8-byte Long[2] value too big to fit inline. Exercises that the
+    // external value's offset is resolved against the embedded TIFF
+    // header (base == value_offset + header_len), the same origin the
+    // IFD offset itself is resolved against, rather than against the
+    // MakerNote value's own start.
+    #[test]
+    fn nikon_external_value_is_relative_to_embedded_tiff_header() {
+        let mut data = Vec::new();
+        data.extend_from_slice(b"Nikon\0");
+        data.extend_from_slice(&[0x02, 0x10, 0x00, 0x00]); // header_len == 10
+        data.extend_from_slice(b"II"); // embedded TIFF header starts here (offset 10)
+        data.extend_from_slice(&[0x2a, 0x00]);
+        data.extend_from_slice(&8u32.to_le_bytes()); // rel_ifd_offset -> IFD at 10+8=18
+        data.extend_from_slice(&1u16.to_le_bytes()); // IFD entry count
+        data.extend_from_slice(&1u16.to_le_bytes()); // tag
+        data.extend_from_slice(&4u16.to_le_bytes()); // type == Long (unitlen 4)
+        data.extend_from_slice(&2u32.to_le_bytes()); // count == 2 -> vallen 8, external
+        data.extend_from_slice(&22u32.to_le_bytes()); // rel_ofs, relative to base == 10
+        // External value lives right after the IFD entry, at offset 32
+        // (== base 10 + rel_ofs 22).
+        data.extend_from_slice(&0x11111111u32.to_le_bytes());
+        data.extend_from_slice(&0x22222222u32.to_le_bytes());
+
+        let len = data.len();
+        let entries = parse(&data, 0, len, true, 0).unwrap();
+        assert_eq!(entries.len(), 1);
+        let field = entries[0].ref_field(&data, true);
+        assert_eq!(field.tag, Tag(Context::MakerNote, 1));
+        match field.value {
+            Value::Long(ref v) => assert_eq!(v, &[0x11111111, 0x22222222]),
+            ref v => panic!("wrong variant {:?}", v),
+        }
+    }
+}
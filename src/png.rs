@@ -0,0 +1,140 @@
+use std::io::Read;
+
+use crate::Error;
+
+const PNG_SIG: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+const EXIF_CHUNK: [u8; 4] = *b"eXIf";
+const IHDR_CHUNK: [u8; 4] = *b"IHDR";
+
+pub fn is_png(buf: &[u8]) -> bool {
+    buf.starts_with(&PNG_SIG)
+}
+
+/// Extracts the raw Exif (TIFF) data from a PNG's `eXIf` chunk.
+pub fn get_exif_attr<R>(reader: &mut R) -> Result<Vec<u8>, Error>
+where
+    R: Read,
+{
+    let mut sig = [0u8; 8];
+    reader.read_exact(&mut sig)?;
+    if sig != PNG_SIG {
+        return Err(Error::InvalidFormat("Not a PNG file"));
+    }
+    for chunk in read_chunks(reader)? {
+        if chunk.typ == EXIF_CHUNK {
+            return Ok(chunk.data);
+        }
+    }
+    Err(Error::NotFound("eXIf chunk not found in PNG"))
+}
+
+/// Returns a new PNG byte stream with `exif_data` (a raw TIFF blob)
+/// embedded as the `eXIf` chunk, replacing any existing one.
+///
+/// The new chunk is placed immediately after `IHDR`, which satisfies
+/// the PNG specification's requirement that `eXIf` precede `PLTE` and
+/// `IDAT`.  All other chunks are preserved byte for byte.
+pub fn set_exif_attr(png_data: &[u8], exif_data: &[u8]) -> Result<Vec<u8>, Error> {
+    if !is_png(png_data) {
+        return Err(Error::InvalidFormat("Not a PNG file"));
+    }
+    let mut chunks = read_chunks(&mut &png_data[PNG_SIG.len()..])?;
+    chunks.retain(|c| c.typ != EXIF_CHUNK);
+    let insert_at = chunks
+        .iter()
+        .position(|c| c.typ == IHDR_CHUNK)
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    chunks.insert(
+        insert_at,
+        Chunk {
+            typ: EXIF_CHUNK,
+            data: exif_data.to_vec(),
+        },
+    );
+
+    let mut out = Vec::with_capacity(png_data.len() + exif_data.len() + 12);
+    out.extend_from_slice(&PNG_SIG);
+    for chunk in &chunks {
+        out.extend_from_slice(&(chunk.data.len() as u32).to_be_bytes());
+        out.extend_from_slice(&chunk.typ);
+        out.extend_from_slice(&chunk.data);
+        let mut crc_input = Vec::with_capacity(4 + chunk.data.len());
+        crc_input.extend_from_slice(&chunk.typ);
+        crc_input.extend_from_slice(&chunk.data);
+        out.extend_from_slice(&crc32(&crc_input).to_be_bytes());
+    }
+    Ok(out)
+}
+
+struct Chunk {
+    typ: [u8; 4],
+    data: Vec<u8>,
+}
+
+fn read_chunks<R>(reader: &mut R) -> Result<Vec<Chunk>, Error>
+where
+    R: Read,
+{
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf)?;
+    let mut pos = 0;
+    let mut chunks = Vec::new();
+    loop {
+        if pos == buf.len() {
+            break;
+        }
+        if buf.len() - pos < 8 {
+            return Err(Error::InvalidFormat("Truncated PNG chunk header"));
+        }
+        let len = u32::from_be_bytes(buf[pos..pos + 4].try_into().unwrap()) as usize;
+        let mut typ = [0u8; 4];
+        typ.copy_from_slice(&buf[pos + 4..pos + 8]);
+        let data_start = pos + 8;
+        if buf.len() - data_start < len + 4 {
+            return Err(Error::InvalidFormat("Truncated PNG chunk data"));
+        }
+        let data = buf[data_start..data_start + len].to_vec();
+        let is_end = typ == *b"IEND";
+        chunks.push(Chunk { typ, data });
+        pos = data_start + len + 4; // Skip the trailing CRC.
+        if is_end {
+            break;
+        }
+    }
+    Ok(chunks)
+}
+
+// A bytewise (IEEE 802.3, polynomial 0xEDB88320) CRC-32 as required for
+// PNG chunk checksums; there is no other user of this algorithm in the
+// crate yet, so no lookup table is kept: each byte's table entry is
+// recomputed on the fly.
+fn crc32(data: &[u8]) -> u32 {
+    fn table_entry(mut n: u32) -> u32 {
+        for _ in 0..8 {
+            n = if n & 1 != 0 {
+                0xedb88320 ^ (n >> 1)
+            } else {
+                n >> 1
+            };
+        }
+        n
+    }
+    let mut crc = 0xffffffffu32;
+    for &byte in data {
+        let idx = ((crc ^ byte as u32) & 0xff) as u32;
+        crc = table_entry(idx) ^ (crc >> 8);
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_known_vector() {
+        // The canonical "123456789" check value for this CRC-32 variant.
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+    }
+}
@@ -94,6 +94,21 @@ impl Reader {
         })
     }
 
+    /// Parses the Exif attributes from a seekable reader without
+    /// buffering the whole TIFF data, only reading a field's value
+    /// bytes when it is actually requested via `LazyExif::get_field`.
+    ///
+    /// This is intended for large TIFF-based RAW files where a caller
+    /// only needs a handful of tags.  Use `read_raw` if the whole
+    /// `Exif` is going to be inspected anyway, since it amortizes the
+    /// cost of a single sequential read.
+    pub fn read_lazy<R>(&self, reader: R) -> Result<crate::lazy::LazyExif<R>, Error>
+    where
+        R: io::BufRead + io::Seek,
+    {
+        crate::lazy::parse(reader)
+    }
+
     /// Parses the Exif attributes from raw Exif data.
     /// If an error occurred, `exif::Error` is returned.
     pub fn read_fuji_raw<R>(&self, reader: &mut R) -> Result<Exif, Error>
@@ -146,6 +161,36 @@ impl Reader {
 
         self.read_raw(buf)
     }
+
+    /// Reads an image file and returns a copy of it with its Exif
+    /// attributes replaced (or inserted, if it had none) by `exif_data`,
+    /// a raw TIFF blob such as the one produced by
+    /// `exif::experimental::Writer`.
+    ///
+    /// Supported formats are JPEG, PNG, WebP, and HEIF/HEIC/AVIF; all
+    /// other segments/chunks/boxes of the container are preserved.
+    /// This is the write-side counterpart to `read_from_container`.
+    pub fn write_to_container<R>(&self, reader: &mut R, exif_data: &[u8]) -> Result<Vec<u8>, Error>
+    where
+        R: io::BufRead + io::Seek,
+    {
+        let mut buf = Vec::new();
+        reader.by_ref().take(4096).read_to_end(&mut buf)?;
+        if jpeg::is_jpeg(&buf) {
+            reader.read_to_end(&mut buf)?;
+            jpeg::set_exif_attr(&buf, exif_data)
+        } else if png::is_png(&buf) {
+            reader.read_to_end(&mut buf)?;
+            png::set_exif_attr(&buf, exif_data)
+        } else if webp::is_webp(&buf) {
+            reader.read_to_end(&mut buf)?;
+            webp::set_exif_attr(&buf, exif_data)
+        } else if isobmff::is_heif(&buf) {
+            isobmff::set_exif_attr(reader, exif_data)
+        } else {
+            Err(Error::InvalidFormat("Unknown or unsupported image format"))
+        }
+    }
 }
 
 #[cfg(test)]
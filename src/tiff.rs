@@ -27,6 +27,7 @@
 use crate::endian::{BigEndian, Endian, LittleEndian};
 use crate::error::Error;
 use crate::ifd::IfdEntry;
+use crate::makernote;
 use crate::parser::{Parse, Parser};
 use crate::tag::{Context, Tag};
 use crate::value::get_type_info;
@@ -139,6 +140,17 @@ impl Parser {
                 Tag::InteropIFDPointer => {
                     self.parse_child_ifd::<E>(data, val, Context::Interop, ifd_num)?
                 }
+                Tag::MakerNote => {
+                    self.parse_maker_note::<E>(data, &val, ifd_num);
+                    self.entries.push(IfdEntry {
+                        field: Field {
+                            tag: tag,
+                            ifd_num: In(ifd_num),
+                            value: val,
+                        }
+                        .into(),
+                    });
+                }
                 _ => self.entries.push(IfdEntry {
                     field: Field {
                         tag: tag,
@@ -183,6 +195,27 @@ impl Parser {
         Ok((tag, val))
     }
 
+    // Best-effort decoding of a `MakerNote` field's embedded vendor IFD.
+    // A MakerNote without a recognized vendor signature is parsed as a
+    // headerless IFD; data that fails to parse is silently ignored: the
+    // raw value is still kept as an opaque field (pushed by the
+    // caller), so nothing is lost, only the vendor tags stay
+    // unreachable.
+    fn parse_maker_note<E>(&mut self, data: &[u8], val: &Value, ifd_num: u16)
+    where
+        E: Endian,
+    {
+        if let Value::Unknown(typ, cnt, ofs) = *val {
+            let (unitlen, _) = get_type_info::<E>(typ);
+            let value_len = unitlen * cnt as usize;
+            if let Ok(extra) =
+                makernote::parse(data, ofs as usize, value_len, self.little_endian, ifd_num)
+            {
+                self.entries.extend(extra);
+            }
+        }
+    }
+
     fn parse_child_ifd<E>(
         &mut self,
         data: &[u8],
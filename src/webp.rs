@@ -0,0 +1,141 @@
+use std::io::Read;
+
+use crate::Error;
+
+const RIFF: [u8; 4] = *b"RIFF";
+const WEBP: [u8; 4] = *b"WEBP";
+const EXIF_CHUNK: [u8; 4] = *b"EXIF";
+
+pub fn is_webp(buf: &[u8]) -> bool {
+    buf.len() >= 12 && buf[0..4] == RIFF && buf[8..12] == WEBP
+}
+
+/// Extracts the raw Exif (TIFF) data from a WebP's `EXIF` chunk.
+pub fn get_exif_attr<R>(reader: &mut R) -> Result<Vec<u8>, Error>
+where
+    R: Read,
+{
+    let mut hdr = [0u8; 12];
+    reader.read_exact(&mut hdr)?;
+    if !is_webp(&hdr) {
+        return Err(Error::InvalidFormat("Not a WebP file"));
+    }
+    for chunk in read_chunks(reader)? {
+        if chunk.fourcc == EXIF_CHUNK {
+            return Ok(chunk.data);
+        }
+    }
+    Err(Error::NotFound("EXIF chunk not found in WebP"))
+}
+
+/// Returns a new WebP byte stream with `exif_data` (a raw TIFF blob)
+/// embedded as the `EXIF` chunk, replacing any existing one, and
+/// updates the overall RIFF size.
+///
+/// If the container has a `VP8X` chunk, its Exif metadata flag is set.
+/// Simple-format (non-extended) WebP files are extended with a minimal
+/// `VP8X` chunk so readers know to look for `EXIF`.
+pub fn set_exif_attr(webp_data: &[u8], exif_data: &[u8]) -> Result<Vec<u8>, Error> {
+    if !is_webp(webp_data) {
+        return Err(Error::InvalidFormat("Not a WebP file"));
+    }
+    let mut chunks = read_chunks(&mut &webp_data[12..])?;
+    chunks.retain(|c| c.fourcc != EXIF_CHUNK);
+
+    if let Some(vp8x) = chunks.iter_mut().find(|c| c.fourcc == *b"VP8X") {
+        if vp8x.data.len() >= 1 {
+            vp8x.data[0] |= 1 << 3; // Exif metadata present [bit 3].
+        }
+    } else {
+        let (width, height) = simple_format_dimensions(&chunks)?;
+        let mut data = vec![0u8; 10];
+        data[0] = 1 << 3; // Exif metadata present [bit 3].
+        data[4..7].copy_from_slice(&(width - 1).to_le_bytes()[..3]);
+        data[7..10].copy_from_slice(&(height - 1).to_le_bytes()[..3]);
+        let insert_at = chunks
+            .iter()
+            .position(|c| c.fourcc == *b"VP8 " || c.fourcc == *b"VP8L")
+            .unwrap_or(0);
+        chunks.insert(insert_at, Chunk { fourcc: *b"VP8X", data });
+    }
+
+    chunks.push(Chunk {
+        fourcc: EXIF_CHUNK,
+        data: exif_data.to_vec(),
+    });
+
+    let mut payload = Vec::new();
+    for chunk in &chunks {
+        payload.extend_from_slice(&chunk.fourcc);
+        payload.extend_from_slice(&(chunk.data.len() as u32).to_le_bytes());
+        payload.extend_from_slice(&chunk.data);
+        if chunk.data.len() % 2 != 0 {
+            payload.push(0);
+        }
+    }
+
+    let mut out = Vec::with_capacity(12 + payload.len());
+    out.extend_from_slice(&RIFF);
+    out.extend_from_slice(&((WEBP.len() + payload.len()) as u32).to_le_bytes());
+    out.extend_from_slice(&WEBP);
+    out.extend_from_slice(&payload);
+    Ok(out)
+}
+
+struct Chunk {
+    fourcc: [u8; 4],
+    data: Vec<u8>,
+}
+
+// Reads the canvas width/height out of a simple-format WebP's `VP8 ` or
+// `VP8L` chunk, needed to fill in the required dimension fields of the
+// `VP8X` chunk synthesized for it.
+fn simple_format_dimensions(chunks: &[Chunk]) -> Result<(u32, u32), Error> {
+    if let Some(vp8) = chunks.iter().find(|c| c.fourcc == *b"VP8 ") {
+        if vp8.data.len() < 10 {
+            return Err(Error::InvalidFormat("Truncated VP8 chunk"));
+        }
+        // Lossy key frame header: 3-byte frame tag, 3-byte start code,
+        // then 14-bit width/height (top 2 bits are a scaling factor).
+        let width = u16::from_le_bytes(vp8.data[6..8].try_into().unwrap()) & 0x3fff;
+        let height = u16::from_le_bytes(vp8.data[8..10].try_into().unwrap()) & 0x3fff;
+        return Ok((width as u32, height as u32));
+    }
+    if let Some(vp8l) = chunks.iter().find(|c| c.fourcc == *b"VP8L") {
+        if vp8l.data.len() < 5 {
+            return Err(Error::InvalidFormat("Truncated VP8L chunk"));
+        }
+        // 1-byte signature (0x2f) then a 4-byte little-endian bitfield:
+        // 14-bit width-1, 14-bit height-1, 1-bit alpha, 3-bit version.
+        let bits = u32::from_le_bytes(vp8l.data[1..5].try_into().unwrap());
+        let width = (bits & 0x3fff) + 1;
+        let height = ((bits >> 14) & 0x3fff) + 1;
+        return Ok((width, height));
+    }
+    Err(Error::InvalidFormat(
+        "No VP8 or VP8L chunk found to synthesize VP8X from",
+    ))
+}
+
+fn read_chunks<R>(reader: &mut R) -> Result<Vec<Chunk>, Error>
+where
+    R: Read,
+{
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf)?;
+    let mut pos = 0;
+    let mut chunks = Vec::new();
+    while buf.len() - pos >= 8 {
+        let mut fourcc = [0u8; 4];
+        fourcc.copy_from_slice(&buf[pos..pos + 4]);
+        let len = u32::from_le_bytes(buf[pos + 4..pos + 8].try_into().unwrap()) as usize;
+        let data_start = pos + 8;
+        if buf.len() - data_start < len {
+            return Err(Error::InvalidFormat("Truncated WebP chunk"));
+        }
+        let data = buf[data_start..data_start + len].to_vec();
+        chunks.push(Chunk { fourcc, data });
+        pos = data_start + len + len % 2;
+    }
+    Ok(chunks)
+}
@@ -0,0 +1,481 @@
+//! Serializes `Field`s into a TIFF/Exif byte stream.
+//!
+//! This is the write-side counterpart to `crate::parser` and
+//! `crate::tiff::parse_exif`: given a set of `Field`s grouped by their
+//! tag's `Context` and `In` (IFD number), `Writer` lays out IFD0 plus
+//! the Exif, GPS, and Interop sub-IFDs, splits any encoded value larger
+//! than 4 bytes into the external value area, and patches the 4-byte
+//! offset fields so the result is a valid, self-contained TIFF header
+//! that `Reader::read_raw` can parse back.
+
+use std::io::Write;
+
+use crate::ifd::{Field, In};
+use crate::tag::Context;
+use crate::value::Value;
+use crate::{Error, Tag};
+
+// TIFF type codes [EXIF23 4.6.2].
+const FMT_BYTE: u16 = 1;
+const FMT_ASCII: u16 = 2;
+const FMT_SHORT: u16 = 3;
+const FMT_LONG: u16 = 4;
+const FMT_RATIONAL: u16 = 5;
+const FMT_UNDEFINED: u16 = 7;
+const FMT_SRATIONAL: u16 = 10;
+const FMT_FLOAT: u16 = 11;
+const FMT_DOUBLE: u16 = 12;
+
+// A fully encoded directory entry: (tag, type code, count, raw value bytes
+// in the target byte order, not yet padded or split).
+type RawEntry = (u16, u16, u32, Vec<u8>);
+
+/// Serializes `Field`s into a TIFF/Exif byte stream.
+///
+/// `Writer` only understands the IFD layout that `Reader` itself
+/// produces: IFD0 (`Context::Tiff`, `In::PRIMARY`), an optional
+/// thumbnail IFD (`Context::Tiff`, `In::THUMBNAIL`), and the Exif, GPS,
+/// and Interop sub-IFDs pointed to from IFD0/the Exif IFD.  Fields in
+/// any other context or IFD number are rejected.
+///
+/// # Examples
+/// ```
+/// use exif::experimental::Writer;
+/// use exif::{Field, In, Tag, Value};
+///
+/// let image_width = Field {
+///     tag: Tag::ImageWidth,
+///     ifd_num: In::PRIMARY,
+///     value: Value::Short(vec![123]),
+/// };
+/// let mut writer = Writer::new();
+/// writer.push_field(&image_width);
+/// let mut buf = Vec::new();
+/// writer.write(&mut buf).unwrap();
+/// assert_eq!(&buf[0..4], b"II*\0");
+/// ```
+pub struct Writer<'a> {
+    fields: Vec<&'a Field>,
+    little_endian: bool,
+}
+
+impl<'a> Default for Writer<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> Writer<'a> {
+    /// Creates a new, empty `Writer` that emits little-endian TIFF data.
+    pub fn new() -> Self {
+        Self {
+            fields: Vec::new(),
+            little_endian: true,
+        }
+    }
+
+    /// Sets whether the serialized TIFF structure uses little-endian
+    /// (`true`) or big-endian (`false`) byte order.
+    pub fn set_little_endian(&mut self, little_endian: bool) {
+        self.little_endian = little_endian;
+    }
+
+    /// Adds a field to be written.  Fields are grouped into IFDs by
+    /// their tag's `Context` and their `ifd_num`.
+    pub fn push_field(&mut self, field: &'a Field) {
+        self.fields.push(field);
+    }
+
+    /// Serializes all the pushed fields and writes the resulting TIFF
+    /// byte stream to `w`.
+    pub fn write<W>(&self, w: &mut W) -> Result<(), Error>
+    where
+        W: Write,
+    {
+        let buf = self.build()?;
+        w.write_all(&buf)?;
+        Ok(())
+    }
+
+    fn write_u16(&self, buf: &mut Vec<u8>, v: u16) {
+        if self.little_endian {
+            buf.extend_from_slice(&v.to_le_bytes());
+        } else {
+            buf.extend_from_slice(&v.to_be_bytes());
+        }
+    }
+
+    fn write_u32(&self, buf: &mut Vec<u8>, v: u32) {
+        if self.little_endian {
+            buf.extend_from_slice(&v.to_le_bytes());
+        } else {
+            buf.extend_from_slice(&v.to_be_bytes());
+        }
+    }
+
+    fn encode_u32(&self, v: u32) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(4);
+        self.write_u32(&mut buf, v);
+        buf
+    }
+
+    // Encodes a Value into its TIFF type code, element count, and raw
+    // value bytes in the writer's byte order.
+    fn encode_value(&self, value: &Value) -> Result<(u16, u32, Vec<u8>), Error> {
+        let mut buf = Vec::new();
+        let type_code = match *value {
+            Value::Byte(ref v) => {
+                buf.extend_from_slice(v);
+                FMT_BYTE
+            }
+            Value::Ascii(ref strs) => {
+                for (i, s) in strs.iter().enumerate() {
+                    if i > 0 {
+                        buf.push(0);
+                    }
+                    buf.extend_from_slice(s);
+                }
+                buf.push(0);
+                FMT_ASCII
+            }
+            Value::Short(ref v) => {
+                for &x in v {
+                    self.write_u16(&mut buf, x);
+                }
+                FMT_SHORT
+            }
+            Value::Long(ref v) => {
+                for &x in v {
+                    self.write_u32(&mut buf, x);
+                }
+                FMT_LONG
+            }
+            Value::Rational(ref v) => {
+                for r in v {
+                    self.write_u32(&mut buf, r.num);
+                    self.write_u32(&mut buf, r.denom);
+                }
+                FMT_RATIONAL
+            }
+            Value::SRational(ref v) => {
+                for r in v {
+                    self.write_u32(&mut buf, r.num as u32);
+                    self.write_u32(&mut buf, r.denom as u32);
+                }
+                FMT_SRATIONAL
+            }
+            Value::Undefined(ref v, _) => {
+                buf.extend_from_slice(v);
+                FMT_UNDEFINED
+            }
+            Value::Float(ref v) => {
+                for &x in v {
+                    buf.extend_from_slice(&if self.little_endian {
+                        x.to_le_bytes()
+                    } else {
+                        x.to_be_bytes()
+                    });
+                }
+                FMT_FLOAT
+            }
+            Value::Double(ref v) => {
+                for &x in v {
+                    buf.extend_from_slice(&if self.little_endian {
+                        x.to_le_bytes()
+                    } else {
+                        x.to_be_bytes()
+                    });
+                }
+                FMT_DOUBLE
+            }
+            Value::Unknown(..) => {
+                return Err(Error::InvalidFormat(
+                    "cannot serialize a field whose value was never parsed",
+                ))
+            }
+        };
+        let unitlen = match type_code {
+            FMT_SHORT => 2,
+            FMT_LONG | FMT_FLOAT => 4,
+            FMT_RATIONAL | FMT_SRATIONAL | FMT_DOUBLE => 8,
+            _ => 1,
+        };
+        let count = (buf.len() / unitlen) as u32;
+        Ok((type_code, count, buf))
+    }
+
+    fn entries_for(&self, fields: &[&Field]) -> Result<Vec<RawEntry>, Error> {
+        fields
+            .iter()
+            .map(|f| {
+                let (type_code, count, bytes) = self.encode_value(&f.value)?;
+                Ok((f.tag.1, type_code, count, bytes))
+            })
+            .collect()
+    }
+
+    // The size in bytes of an IFD's directory (count + 12-byte entries
+    // + next-IFD offset), not including its external value area.
+    fn ifd_dir_size(n: usize) -> usize {
+        2 + 12 * n + 4
+    }
+
+    // The size of the external value area for a set of entries: the
+    // even-padded length of every value that doesn't fit inline.
+    fn external_size(entries: &[RawEntry]) -> usize {
+        entries
+            .iter()
+            .map(|(_, _, _, bytes)| {
+                if bytes.len() <= 4 {
+                    0
+                } else {
+                    bytes.len() + bytes.len() % 2
+                }
+            })
+            .sum()
+    }
+
+    fn emit_ifd(
+        &self,
+        buf: &mut Vec<u8>,
+        mut entries: Vec<RawEntry>,
+        next_ifd_offset: u32,
+        external_offset: usize,
+    ) {
+        entries.sort_by_key(|&(tag, ..)| tag);
+        self.write_u16(buf, entries.len() as u16);
+        let mut external_data = Vec::new();
+        for (tag, type_code, count, bytes) in &entries {
+            self.write_u16(buf, *tag);
+            self.write_u16(buf, *type_code);
+            self.write_u32(buf, *count);
+            if bytes.len() <= 4 {
+                let mut inline = bytes.clone();
+                inline.resize(4, 0);
+                buf.extend_from_slice(&inline);
+            } else {
+                let ofs = external_offset + external_data.len();
+                self.write_u32(buf, ofs as u32);
+                external_data.extend_from_slice(bytes);
+                if bytes.len() % 2 != 0 {
+                    external_data.push(0);
+                }
+            }
+        }
+        self.write_u32(buf, next_ifd_offset);
+        buf.extend_from_slice(&external_data);
+    }
+
+    fn build(&self) -> Result<Vec<u8>, Error> {
+        let mut primary = Vec::new();
+        let mut thumbnail = Vec::new();
+        let mut exif = Vec::new();
+        let mut gps = Vec::new();
+        let mut interop = Vec::new();
+        for &f in &self.fields {
+            match (f.tag.0, f.ifd_num) {
+                (Context::Tiff, In::PRIMARY) => primary.push(f),
+                (Context::Tiff, In::THUMBNAIL) => thumbnail.push(f),
+                (Context::Exif, In::PRIMARY) => exif.push(f),
+                (Context::Gps, In::PRIMARY) => gps.push(f),
+                (Context::Interop, In::PRIMARY) => interop.push(f),
+                _ => {
+                    return Err(Error::InvalidFormat(
+                        "Writer supports only the primary/thumbnail/Exif/GPS/Interop IFDs",
+                    ))
+                }
+            }
+        }
+
+        let has_interop = !interop.is_empty();
+        let has_exif = !exif.is_empty() || has_interop;
+        let has_gps = !gps.is_empty();
+        let has_thumbnail = !thumbnail.is_empty();
+
+        let mut primary_entries = self.entries_for(&primary)?;
+        let thumbnail_entries = self.entries_for(&thumbnail)?;
+        let mut exif_entries = self.entries_for(&exif)?;
+        let interop_entries = self.entries_for(&interop)?;
+        let gps_entries = self.entries_for(&gps)?;
+
+        let header_size = 8;
+        let primary_ifd_offset = header_size;
+        // Reserve the pointer entries up front so the directory sizes
+        // below already account for them, remembering their indices so
+        // the placeholder offsets can be patched in once known.
+        let exif_ptr_idx = if has_exif {
+            primary_entries.push((Tag::ExifIFDPointer.1, FMT_LONG, 1, vec![0; 4]));
+            Some(primary_entries.len() - 1)
+        } else {
+            None
+        };
+        let gps_ptr_idx = if has_gps {
+            primary_entries.push((Tag::GPSInfoIFDPointer.1, FMT_LONG, 1, vec![0; 4]));
+            Some(primary_entries.len() - 1)
+        } else {
+            None
+        };
+        let interop_ptr_idx = if has_interop {
+            exif_entries.push((Tag::InteropIFDPointer.1, FMT_LONG, 1, vec![0; 4]));
+            Some(exif_entries.len() - 1)
+        } else {
+            None
+        };
+
+        let primary_dir_size = Self::ifd_dir_size(primary_entries.len());
+        let primary_external_size = Self::external_size(&primary_entries);
+        let primary_external_offset = primary_ifd_offset + primary_dir_size;
+
+        let thumbnail_ifd_offset = primary_external_offset + primary_external_size;
+        let thumbnail_dir_size = if has_thumbnail {
+            Self::ifd_dir_size(thumbnail_entries.len())
+        } else {
+            0
+        };
+        let thumbnail_external_size = if has_thumbnail {
+            Self::external_size(&thumbnail_entries)
+        } else {
+            0
+        };
+        let thumbnail_external_offset = thumbnail_ifd_offset + thumbnail_dir_size;
+        let after_thumbnail = thumbnail_external_offset + thumbnail_external_size;
+
+        let exif_ifd_offset = after_thumbnail;
+        let exif_dir_size = if has_exif {
+            Self::ifd_dir_size(exif_entries.len())
+        } else {
+            0
+        };
+        let exif_external_size = if has_exif {
+            Self::external_size(&exif_entries)
+        } else {
+            0
+        };
+        let exif_external_offset = exif_ifd_offset + exif_dir_size;
+        let after_exif = exif_external_offset + exif_external_size;
+
+        let interop_ifd_offset = after_exif;
+        let interop_dir_size = if has_interop {
+            Self::ifd_dir_size(interop_entries.len())
+        } else {
+            0
+        };
+        let interop_external_size = if has_interop {
+            Self::external_size(&interop_entries)
+        } else {
+            0
+        };
+        let interop_external_offset = interop_ifd_offset + interop_dir_size;
+        let after_interop = interop_external_offset + interop_external_size;
+
+        let gps_ifd_offset = after_interop;
+        let gps_dir_size = if has_gps {
+            Self::ifd_dir_size(gps_entries.len())
+        } else {
+            0
+        };
+        let gps_external_size = if has_gps {
+            Self::external_size(&gps_entries)
+        } else {
+            0
+        };
+        let gps_external_offset = gps_ifd_offset + gps_dir_size;
+
+        // Back-patch the pointer placeholders now that every IFD's
+        // offset is known.
+        if let Some(idx) = exif_ptr_idx {
+            primary_entries[idx].3 = self.encode_u32(exif_ifd_offset as u32);
+        }
+        if let Some(idx) = gps_ptr_idx {
+            primary_entries[idx].3 = self.encode_u32(gps_ifd_offset as u32);
+        }
+        if let Some(idx) = interop_ptr_idx {
+            exif_entries[idx].3 = self.encode_u32(interop_ifd_offset as u32);
+        }
+
+        let mut out = Vec::with_capacity(gps_external_offset + gps_external_size);
+        if self.little_endian {
+            out.extend_from_slice(b"II*\0");
+        } else {
+            out.extend_from_slice(b"MM\0*");
+        }
+        self.write_u32(&mut out, primary_ifd_offset as u32);
+
+        let primary_next = if has_thumbnail {
+            thumbnail_ifd_offset as u32
+        } else {
+            0
+        };
+        self.emit_ifd(&mut out, primary_entries, primary_next, primary_external_offset);
+        if has_thumbnail {
+            self.emit_ifd(&mut out, thumbnail_entries, 0, thumbnail_external_offset);
+        }
+        if has_exif {
+            self.emit_ifd(&mut out, exif_entries, 0, exif_external_offset);
+        }
+        if has_interop {
+            self.emit_ifd(&mut out, interop_entries, 0, interop_external_offset);
+        }
+        if has_gps {
+            self.emit_ifd(&mut out, gps_entries, 0, gps_external_offset);
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{In, Value};
+
+    fn field(tag: crate::Tag, ifd_num: In, value: Value) -> Field {
+        Field {
+            tag,
+            ifd_num,
+            value,
+        }
+    }
+
+    #[test]
+    fn simple_short_field() {
+        let f = field(
+            crate::Tag::ImageWidth,
+            In::PRIMARY,
+            Value::Short(vec![123]),
+        );
+        let mut writer = Writer::new();
+        writer.push_field(&f);
+        let mut buf = Vec::new();
+        writer.write(&mut buf).unwrap();
+        assert_eq!(&buf[0..4], b"II*\0");
+        let (fields, le) = crate::parse_exif(&buf).unwrap();
+        assert!(le);
+        assert_eq!(fields.len(), 1);
+        assert_eq!(fields[0].tag, crate::Tag::ImageWidth);
+        match fields[0].value {
+            Value::Short(ref v) => assert_eq!(v, &[123]),
+            ref v => panic!("wrong variant {:?}", v),
+        }
+    }
+
+    #[test]
+    fn big_endian_roundtrip() {
+        let f = field(
+            crate::Tag::ImageDescription,
+            In::PRIMARY,
+            Value::Ascii(vec![b"hello".to_vec()]),
+        );
+        let mut writer = Writer::new();
+        writer.set_little_endian(false);
+        writer.push_field(&f);
+        let mut buf = Vec::new();
+        writer.write(&mut buf).unwrap();
+        assert_eq!(&buf[0..4], b"MM\0*");
+        let (fields, le) = crate::parse_exif(&buf).unwrap();
+        assert!(!le);
+        match fields[0].value {
+            Value::Ascii(ref v) => assert_eq!(v, &[b"hello".to_vec()]),
+            ref v => panic!("wrong variant {:?}", v),
+        }
+    }
+}